@@ -1,14 +1,18 @@
-use rand::{self, Rng};
+use rand::Rng;
 
 use tcod::map::Map as FovMap;
 
-use log::MessageLog;
-use map::Map;
+use fire;
+use horde;
+use map::{self, Map};
+use noise;
 use object::Object;
 use object::actor;
+use scheduler::Action;
 
 use consts;
 use util;
+use util::rng;
 
 use game::Game;
 
@@ -16,97 +20,202 @@ use game::Game;
 pub enum Ai {
     Basic,
     Chrysalis,
-    Stunned{previous_ai: Box<Ai>, num_turns: i32},
+    // A living human, see `Object::attitude`
+    Survivor,
 }
 
+/// Let the monster act once, returning what it did so the scheduler can
+/// charge it for the time.
 pub fn take_turn(monster_id: usize, game: &mut Game, actors: &mut [Object],
-                fov_map: &FovMap) {
+                fov_map: &FovMap) -> Action {
     if let Some(ai) = actors[monster_id].ai.take() {
-        let new_ai = match ai {
+        let (new_ai, action) = match ai {
             Ai::Basic =>
                 basic(monster_id, game, actors, fov_map),
             Ai::Chrysalis => chrysalis(monster_id, game, actors, fov_map),
-            Ai::Stunned{previous_ai, num_turns} => stunned(
-                monster_id, game, actors, previous_ai, num_turns)
+            Ai::Survivor => survivor(monster_id, game, actors, fov_map),
         };
         actors[monster_id].ai = Some(new_ai);
+        action
+    } else {
+        Action::Wait
     }
 }
 
-fn move_randomly(monster_id: usize, map: &Map, actors: &mut[Object]) {
-    actor::move_by(monster_id,
-            rand::thread_rng().gen_range(-1, 2),
-            rand::thread_rng().gen_range(-1, 2),
-            map, actors);
-}
+/// Range at which a monster notices a walking player in plain sight.
+const SIGHT_RADIUS: f32 = 4.0;
+/// How far the moan of a zombie that has spotted the player carries.
+const ALERT_VOLUME: i32 = 6;
 
-fn basic(monster_id: usize, game: &mut Game, actors: &mut [Object],
-            fov_map: &FovMap) -> Ai {
+/// Whether the monster notices the player. The player has to be in view,
+/// and close enough given how conspicuously they are moving.
+fn spots_player(monster_id: usize, actors: &[Object], fov_map: &FovMap)
+                -> bool {
     let (monster_x, monster_y) = actors[monster_id].pos();
-    if fov_map.is_in_fov(monster_x, monster_y) {
-        if actors[monster_id].distance_to(&actors[consts::PLAYER]) >= 2.0 {
-            let (player_x, player_y) = actors[consts::PLAYER].pos();
-            actor::move_towards(monster_id, player_x, player_y,
-                                &mut game.map, actors);
-        } else if actors[consts::PLAYER].fighter.map_or(false, |f| f.hp > 0) {
-            let (monster, player) = util::mut_two(
-                monster_id, consts::PLAYER, actors);
-            monster.attack(player, &mut game.log);
-        }
-    } else {
-        move_randomly(monster_id, &game.map, actors);
-    }
-    Ai::Basic
+    let player = &actors[consts::PLAYER];
+    fov_map.is_in_fov(monster_x, monster_y) &&
+        actors[monster_id].distance_to(player) <=
+        SIGHT_RADIUS * player.move_mode.visibility()
 }
 
-fn chrysalis(monster_id: usize, game: &mut Game, actors: &mut [Object],
-                fov_map: &FovMap) -> Ai {
-    let (monster_x, monster_y) = actors[monster_id].pos();
-    if fov_map.is_in_fov(monster_x, monster_y) {
-        if actors[monster_id].distance_to(&actors[consts::PLAYER]) >= 2.0 {
-            let (player_x, player_y) = actors[consts::PLAYER].pos();
-            actor::move_towards(monster_id, player_x, player_y,
-                                &mut game.map, actors);
-        } else if actors[consts::PLAYER].fighter.map_or(false, |f| f.hp > 0) {
-            let (monster, player) = util::mut_two(monster_id,
-                                                  consts::PLAYER, actors);
-            monster.attack(player, &mut game.log);
-        }
+/// How aware of the player the monster at `monster_id` looks to them.
+pub fn alertness(monster_id: usize, game: &Game, actors: &[Object],
+                 fov_map: &FovMap) -> &'static str {
+    let monster = &actors[monster_id];
+    match (&monster.ai, monster.attitude) {
+        (&Some(Ai::Survivor), Some(actor::Attitude::Friendly)) => "friendly",
+        (&Some(Ai::Survivor), Some(actor::Attitude::Hostile)) => "hostile",
+        (&Some(Ai::Survivor), _) => "wary",
+        (&Some(_), _) if spots_player(monster_id, actors, fov_map) =>
+            "has noticed you",
+        (&Some(Ai::Chrysalis), _) => "dormant",
+        (&Some(_), _) if horde::is_alerted(&game.hordes, monster) =>
+            "restless",
+        (&Some(_), _) => "unaware of you",
+        (&None, _) => "",
     }
+}
 
-    Ai::Chrysalis
+fn move_randomly(monster_id: usize, game: &mut Game, actors: &mut [Object])
+                 -> Action {
+    let dx = rng::game_rng().gen_range(-1, 2);
+    let dy = rng::game_rng().gen_range(-1, 2);
+    let (x, y) = actors[monster_id].pos();
+    if !fire::is_burning(x + dx, y + dy, &game.map) {
+        actor::move_by(monster_id, dx, dy, game, actors);
+    }
+    Action::Move
 }
 
-fn chrysalis_awake(monster_id: usize, game: &mut Game, actors: &mut [Object],
-            fov_map: &FovMap, previous_ai: Box<Ai>, num_turns: i32) -> Ai {
-    let (monster_x, monster_y) = actors[monster_id].pos();
-    if fov_map.is_in_fov(monster_x, monster_y) {
-        if actors[monster_id].distance_to(&actors[consts::PLAYER]) >= 2.0 {
-            let (player_x, player_y) = actors[consts::PLAYER].pos();
-            actor::move_towards(monster_id, player_x, player_y,
-                                &mut game.map, actors);
-        } else if actors[consts::PLAYER].fighter.map_or(false, |f| f.hp > 0) {
-            let (monster, player) = util::mut_two(
-                monster_id, consts::PLAYER, actors);
-            monster.attack(player, &mut game.log);
-        }
+/// Close in on the player and attack once next to them.
+fn chase_player(monster_id: usize, game: &mut Game, actors: &mut [Object])
+                -> Action {
+    if actors[monster_id].distance_to(&actors[consts::PLAYER]) >= 2.0 {
+        let (player_x, player_y) = actors[consts::PLAYER].pos();
+        actor::move_towards(monster_id, player_x, player_y, game, actors);
+        Action::Move
+    } else if actors[consts::PLAYER].fighter.map_or(false, |f| f.hp > 0) {
+        let (monster, player) = util::mut_two(
+            monster_id, consts::PLAYER, actors);
+        monster.attack(player, &mut game.log);
+        Action::Attack
     } else {
-        move_randomly(monster_id, &game.map, actors);
+        Action::Wait
     }
-    Ai::Chrysalis
 }
 
-fn stunned(monster_id: usize, game: &mut Game, actors: &mut [Object],
-               previous_ai: Box<Ai>, num_turns: i32)
-               -> Ai {
+/// Moan loud enough for the rest of the horde and any other zombie nearby
+/// to come and see what the fuss is about.
+fn alert_others(monster_id: usize, game: &mut Game, actors: &[Object]) {
+    let (x, y) = actors[monster_id].pos();
+    let (player_x, player_y) = actors[consts::PLAYER].pos();
+    horde::alert(&mut game.hordes, &actors[monster_id], player_x, player_y);
+    noise::make_noise(&mut game.noises, x, y, ALERT_VOLUME);
+}
 
-    if num_turns >= 0 {
-        game.log.status_change(format!("The {} is stunned!",
-                             actors[monster_id].name));
-        Ai::Stunned{previous_ai: previous_ai, num_turns: num_turns - 1}
+/// Head for wherever the horde is going, milling about once there.
+fn follow_horde(monster_id: usize, goal: (i32, i32), game: &mut Game,
+                actors: &mut [Object]) -> Action {
+    let (goal_x, goal_y) = goal;
+    if actors[monster_id].distance(goal_x, goal_y) < 2.0 {
+        move_randomly(monster_id, game, actors)
     } else {
-        game.log.status_change(format!("The {} is no longer stunned!",
-                                  actors[monster_id].name));
-        *previous_ai
+        actor::move_towards(monster_id, goal_x, goal_y, game, actors);
+        Action::Move
     }
 }
+
+fn basic(monster_id: usize, game: &mut Game, actors: &mut [Object],
+            fov_map: &FovMap) -> (Ai, Action) {
+    let action = if spots_player(monster_id, actors, fov_map) {
+        alert_others(monster_id, game, actors);
+        chase_player(monster_id, game, actors)
+    } else if let Some(victim_id) = survivor_next_to(monster_id, actors) {
+        let (monster, victim) = util::mut_two(monster_id, victim_id, actors);
+        monster.attack(victim, &mut game.log);
+        Action::Attack
+    } else if let Some(noise) = noise::loudest_heard(&actors[monster_id],
+                                                     &game.noises) {
+        horde::alert(&mut game.hordes, &actors[monster_id], noise.x, noise.y);
+        actor::move_towards(monster_id, noise.x, noise.y, game, actors);
+        Action::Move
+    } else if let Some(goal) = horde::goal(&game.hordes, &actors[monster_id]) {
+        follow_horde(monster_id, goal, game, actors)
+    } else {
+        move_randomly(monster_id, game, actors)
+    };
+    (Ai::Basic, action)
+}
+
+/// A living survivor within reach of the zombie at `monster_id`.
+fn survivor_next_to(monster_id: usize, actors: &[Object]) -> Option<usize> {
+    let (x, y) = actors[monster_id].pos();
+    actors.iter().position(|object| {
+        object.alive && object.attitude.is_some() &&
+            (object.x - x).abs() <= 1 && (object.y - y).abs() <= 1
+    })
+}
+
+/// The closest zombie the survivor at `survivor_id` can see coming.
+fn nearest_zombie(survivor_id: usize, map: &Map, actors: &[Object])
+                  -> Option<usize> {
+    let survivor = &actors[survivor_id];
+    let mut nearest = None;
+    let mut nearest_distance = SIGHT_RADIUS;
+    for (id, object) in actors.iter().enumerate() {
+        if id != consts::PLAYER && object.alive && object.attitude.is_none() &&
+            object.ai.is_some() {
+                let distance = survivor.distance_to(object);
+                if distance <= nearest_distance &&
+                    map::line_of_sight(survivor.pos(), object.pos(), map,
+                                       actors) {
+                    nearest = Some(id);
+                    nearest_distance = distance;
+                }
+            }
+    }
+    nearest
+}
+
+/// Hostile survivors go after the player. Everyone else keeps out of the
+/// zombies' way and otherwise stays more or less put.
+fn survivor(monster_id: usize, game: &mut Game, actors: &mut [Object],
+            fov_map: &FovMap) -> (Ai, Action) {
+    let hostile = actors[monster_id].attitude == Some(actor::Attitude::Hostile);
+    let action = if hostile && spots_player(monster_id, actors, fov_map) {
+        chase_player(monster_id, game, actors)
+    } else if let Some(zombie_id) = nearest_zombie(monster_id, &game.map,
+                                                   actors) {
+        let dx = (actors[monster_id].x - actors[zombie_id].x).signum();
+        let dy = (actors[monster_id].y - actors[zombie_id].y).signum();
+        actor::move_by(monster_id, dx, dy, game, actors);
+        Action::Move
+    } else if rng::game_rng().gen_range(0, 4) == 0 {
+        move_randomly(monster_id, game, actors)
+    } else {
+        Action::Wait
+    };
+    (Ai::Survivor, action)
+}
+
+fn chrysalis(monster_id: usize, game: &mut Game, actors: &mut [Object],
+                fov_map: &FovMap) -> (Ai, Action) {
+    let action = if spots_player(monster_id, actors, fov_map) {
+        chase_player(monster_id, game, actors)
+    } else {
+        Action::Wait
+    };
+
+    (Ai::Chrysalis, action)
+}
+
+fn chrysalis_awake(monster_id: usize, game: &mut Game, actors: &mut [Object],
+            fov_map: &FovMap, previous_ai: Box<Ai>, num_turns: i32)
+                   -> (Ai, Action) {
+    let action = if spots_player(monster_id, actors, fov_map) {
+        chase_player(monster_id, game, actors)
+    } else {
+        move_randomly(monster_id, game, actors)
+    };
+    (Ai::Chrysalis, action)
+}
@@ -0,0 +1,71 @@
+use rustc_serialize::json;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+
+use log;
+use object::Object;
+use object::actor;
+use object::character::{Attributes, Character, Skill};
+use object::load::ObjectTypes;
+
+/// What the player did before the outbreak. Decides their attributes,
+/// the skills they start with and the kit they grabbed on the way out.
+#[derive(Debug, RustcDecodable)]
+pub struct Background {
+    pub name: String,
+    pub description: String,
+    pub attributes: Attributes,
+    pub max_hp: i32,
+    pub skills: Vec<(Skill, i32)>,
+    pub kit: Vec<(String, i32)>,
+}
+
+#[derive(Debug, RustcDecodable)]
+struct JsonBackgrounds {
+    backgrounds: Vec<Background>,
+}
+
+pub fn load_backgrounds(filename: &str) -> Result<Vec<Background>, Box<Error>> {
+    let mut json = String::new();
+    let mut file = try!{ File::open(filename) };
+    try!{ file.read_to_string(&mut json) };
+    let backgrounds: JsonBackgrounds = try!{ json::decode(&json) };
+    Ok(backgrounds.backgrounds)
+}
+
+pub fn create_character(background: &Background) -> Character {
+    let mut character = Character::new(background.attributes);
+    for &(skill, level) in &background.skills {
+        character.training_mut(skill).level = level;
+    }
+    character
+}
+
+/// The starting inventory for `background`, with anything wearable
+/// already equipped. When the kit has more than one thing for a slot, the
+/// first one listed is the one equipped.
+pub fn starting_kit(background: &Background, item_types: &ObjectTypes,
+                    log: &mut log::Messages) -> Vec<Object> {
+    let mut inventory = vec![];
+    for &(ref name, count) in &background.kit {
+        if let Some(class) = item_types.get_class(name) {
+            for _ in 0..count {
+                actor::add_to_inventory(class.create_object(), &mut inventory);
+            }
+        }
+    }
+    for inventory_id in 0..inventory.len() {
+        let slot = match inventory[inventory_id].equipment {
+            Some(ref equipment) => equipment.slot,
+            None => continue,
+        };
+        let taken = inventory.iter().any(|item| {
+            item.equipment.as_ref().map_or(false, |e| e.equipped && e.slot == slot)
+        });
+        if !taken {
+            actor::equip(inventory_id, &mut inventory, log);
+        }
+    }
+    inventory
+}
@@ -0,0 +1,109 @@
+use consts;
+use game::{self, Data, Game};
+use log::MessageLog;
+use object::Object;
+use object::actor::{self, MoveMode, Trade};
+use object::container::{Location, Transfer};
+use object::interact::Interaction;
+use scheduler::Action;
+use sight::Sight;
+
+/// Something the player has decided to do, with every choice they made
+/// along the way filled in. Input is turned into commands before anything
+/// in the game changes, so a run can be recorded and played back.
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Command {
+    // Step in a direction, attacking anything hostile in the way
+    Move(i32, i32),
+    // Do something to whatever stands in the way in a direction
+    Interact(i32, i32, Interaction),
+    Wait,
+    ToggleMoveMode(MoveMode),
+    // One item off the floor by index, or everything
+    PickUp(Option<usize>),
+    // An inventory item, aimed at a tile if it needs a target
+    UseItem(usize, Option<(i32, i32)>),
+    Throw(usize, (i32, i32)),
+    Drop(usize),
+    Fire(i32, i32),
+    // Index into the recipe list
+    Craft(usize),
+    Search(Location, Vec<Transfer>),
+    // The survivor's actor id, and every swap made with them
+    Talk(usize, Vec<Trade>),
+    Stairs,
+}
+
+/// Carry out `command` for the player. Returns the action it took, or
+/// None if no time passed.
+pub fn execute(command: &Command, game: &mut Game, data: &Data,
+               actors: &mut Vec<Object>, sight: &Sight) -> Option<Action> {
+    match *command {
+        Command::Move(dx, dy) =>
+            actor::player_move_or_attack(dx, dy, game, actors),
+        Command::Interact(dx, dy, interaction) =>
+            actor::interact(dx, dy, interaction, game, actors),
+        Command::Wait => Some(Action::Wait),
+        Command::ToggleMoveMode(mode) => {
+            actor::toggle_move_mode(mode, &mut actors[consts::PLAYER],
+                                    &mut game.log);
+            None
+        }
+        Command::PickUp(item_id) => {
+            let (x, y) = actors[consts::PLAYER].pos();
+            let capacity = actor::carry_capacity(&actors[consts::PLAYER]);
+            if let Some(ref mut inventory) = actors[consts::PLAYER].inventory {
+                match item_id {
+                    Some(item_id) => actor::pick_up_item(x, y, item_id,
+                                                         inventory, capacity,
+                                                         game),
+                    None => actor::pick_up_items(x, y, inventory, capacity,
+                                                 game),
+                }
+            }
+            None
+        }
+        Command::UseItem(inventory_id, target) =>
+            actor::use_item(inventory_id, target, game, actors, sight),
+        Command::Throw(inventory_id, target) =>
+            actor::use_item(inventory_id, Some(target), game, actors, sight),
+        Command::Drop(inventory_id) => {
+            let (x, y) = actors[consts::PLAYER].pos();
+            if let Some(ref mut inventory) = actors[consts::PLAYER].inventory {
+                actor::drop_item(x, y, inventory_id, game, inventory);
+            }
+            None
+        }
+        Command::Fire(x, y) => if actor::player_fire(x, y, game, actors) {
+            Some(Action::Shoot)
+        } else {
+            None
+        },
+        Command::Craft(recipe_id) =>
+            match actor::player_craft(recipe_id, data, game, actors) {
+                0 => None,
+                turns => Some(Action::Craft(turns)),
+            },
+        Command::Search(location, ref transfers) =>
+            match actor::search_location(location, transfers, game, actors) {
+                0 => None,
+                turns => Some(Action::Search(turns)),
+            },
+        Command::Talk(npc_id, ref trades) => {
+            actor::talk_to(npc_id, trades, game, actors);
+            Some(Action::Talk)
+        }
+        Command::Stairs => {
+            let (x, y) = actors[consts::PLAYER].pos();
+            let on_stairs = game.map[x as usize][y as usize].items.iter()
+                .any(|object| object.name == "stairs up");
+            if !on_stairs {
+                game.log.info("There are no stairs here.");
+                return None;
+            }
+            game::next_level(actors, game, data);
+            game.log.success("You climb the stairs to the next floor.");
+            Some(Action::Move)
+        }
+    }
+}
@@ -1,17 +1,11 @@
 
-pub const SCREEN_WIDTH: i32 = 80;
-pub const SCREEN_HEIGHT: i32 = 50;
 pub const LIMIT_FPS: i32 = 20;
 
 pub const BAR_WIDTH: i32 = 20;
-pub const PANEL_HEIGHT: i32 = 7;
-pub const PANEL_Y: i32 = SCREEN_HEIGHT - PANEL_HEIGHT;
 
-pub const MSG_X: i32 = BAR_WIDTH + 2;
-pub const MSG_WIDTH: i32 = SCREEN_WIDTH - BAR_WIDTH - 2;
-pub const MSG_HEIGHT: usize = PANEL_HEIGHT as usize - 1;
-
-pub const MAX_INVENTORY_ITEMS: i32 = 26;
+pub const MENU_PAGE_SIZE: i32 = 26;
 pub const INVENTORY_WIDTH: i32 = 50;
 
 pub const PLAYER: usize = 0;
+// Kilograms a player of average strength can carry before they slow down
+pub const PLAYER_CARRY_CAPACITY: f32 = 25.0;
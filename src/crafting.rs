@@ -0,0 +1,87 @@
+use rustc_serialize::json;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+
+use log::{self, MessageLog};
+use object::Object;
+use object::actor;
+use object::load::ObjectTypes;
+
+/// A way of combining scavenged items into something new. Ingredients are
+/// used up, tools only have to be in the inventory.
+#[derive(Debug, RustcDecodable)]
+pub struct Recipe {
+    pub name: String,
+    pub ingredients: Vec<(String, i32)>,
+    pub tools: Vec<String>,
+    pub output: String,
+    pub output_count: i32,
+    pub turns: i32,
+    pub noise: i32,
+}
+
+#[derive(Debug, RustcDecodable)]
+struct JsonRecipes {
+    recipes: Vec<Recipe>,
+}
+
+pub fn load_recipes(filename: &str) -> Result<Vec<Recipe>, Box<Error>> {
+    let mut json = String::new();
+    let mut file = try!{ File::open(filename) };
+    try!{ file.read_to_string(&mut json) };
+    let recipes: JsonRecipes = try!{ json::decode(&json) };
+    Ok(recipes.recipes)
+}
+
+fn count_of(name: &str, inventory: &[Object]) -> i32 {
+    inventory.iter().filter(|item| item.name == name)
+        .map(|item| item.count).sum()
+}
+
+/// Names of everything `inventory` is short of to make `recipe`.
+pub fn missing(recipe: &Recipe, inventory: &[Object]) -> Vec<String> {
+    let mut missing = vec![];
+    for &(ref name, needed) in &recipe.ingredients {
+        let have = count_of(name, inventory);
+        if have < needed {
+            missing.push(if needed > 1 {
+                format!("{} x{}", name, needed - have)
+            } else {
+                name.clone()
+            });
+        }
+    }
+    for tool in &recipe.tools {
+        if count_of(tool, inventory) == 0 {
+            missing.push(tool.clone());
+        }
+    }
+    missing
+}
+
+pub fn can_craft(recipe: &Recipe, inventory: &[Object]) -> bool {
+    missing(recipe, inventory).is_empty()
+}
+
+/// Use up the ingredients for `recipe` and add its output to the inventory.
+pub fn craft(recipe: &Recipe, inventory: &mut Vec<Object>,
+             item_types: &ObjectTypes, log: &mut log::Messages) {
+    // Recipes are checked against the items when the game starts
+    let output_class = match item_types.get_class(&recipe.output) {
+        Some(class) => class,
+        None => return,
+    };
+    for &(ref name, needed) in &recipe.ingredients {
+        for _ in 0..needed {
+            let item_id = inventory.iter().position(|item| &item.name == name)
+                .unwrap();
+            actor::unequip(item_id, inventory, log);
+            actor::take_one(item_id, inventory);
+        }
+    }
+    for _ in 0..recipe.output_count {
+        actor::add_to_inventory(output_class.create_object(), inventory);
+    }
+    log.success(format!("You made {}.", recipe.name));
+}
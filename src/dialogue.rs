@@ -0,0 +1,53 @@
+use rustc_serialize::json;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+
+/// Something a conversation can lead to besides more talking.
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable)]
+pub enum Outcome {
+    Trade,
+    Leave,
+}
+
+/// A line the player can say. It either moves the conversation on to the
+/// node called `next`, or ends it with an `outcome`.
+#[derive(Debug, RustcDecodable)]
+pub struct Choice {
+    pub text: String,
+    pub next: Option<String>,
+    pub outcome: Option<Outcome>,
+}
+
+#[derive(Debug, RustcDecodable)]
+pub struct Node {
+    pub id: String,
+    pub text: String,
+    pub choices: Vec<Choice>,
+}
+
+/// A conversation tree, starting at the node with id "start".
+#[derive(Debug, RustcDecodable)]
+pub struct Dialogue {
+    pub name: String,
+    pub nodes: Vec<Node>,
+}
+
+impl Dialogue {
+    pub fn node(&self, id: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+}
+
+#[derive(Debug, RustcDecodable)]
+struct JsonDialogues {
+    dialogues: Vec<Dialogue>,
+}
+
+pub fn load_dialogues(filename: &str) -> Result<Vec<Dialogue>, Box<Error>> {
+    let mut json = String::new();
+    let mut file = try!{ File::open(filename) };
+    try!{ file.read_to_string(&mut json) };
+    let dialogues: JsonDialogues = try!{ json::decode(&json) };
+    Ok(dialogues.dialogues)
+}
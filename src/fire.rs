@@ -0,0 +1,105 @@
+use std::cmp;
+
+use rand::Rng;
+
+use log;
+use map::{self, Map};
+use object::{self, Object};
+use object::status;
+use util::rng;
+
+/// Turns a tile burns for with nothing flammable on it, e.g. spilled fuel.
+pub const BASE_BURN_TURNS: i32 = 3;
+/// Turns of smoke left behind once a fire burns out.
+pub const SMOKE_TURNS: i32 = 4;
+/// How far the glow of a fire lights up the tiles around it.
+pub const LIGHT_RADIUS: i32 = 2;
+
+fn in_bounds(x: i32, y: i32) -> bool {
+    x >= 0 && y >= 0 && x < map::FLOOR_WIDTH && y < map::FLOOR_HEIGHT
+}
+
+pub fn is_burning(x: i32, y: i32, map: &Map) -> bool {
+    in_bounds(x, y) && map[x as usize][y as usize].fire > 0
+}
+
+/// Whether (x, y) is close enough to a fire to be lit by it.
+pub fn is_lit(x: i32, y: i32, map: &Map) -> bool {
+    for fire_x in x - LIGHT_RADIUS..x + LIGHT_RADIUS + 1 {
+        for fire_y in y - LIGHT_RADIUS..y + LIGHT_RADIUS + 1 {
+            if is_burning(fire_x, fire_y, map) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// How readily anything on the tile catches fire, 0 to 100.
+fn flammability(x: i32, y: i32, map: &Map) -> i32 {
+    let tile = &map[x as usize][y as usize];
+    tile.items.iter().map(|item| item.flammability)
+        .fold(tile.floor.flammability, cmp::max)
+}
+
+/// Set (x, y) alight. The tile burns longer the more fuel is on it. Solid
+/// walls never burn.
+pub fn ignite(x: i32, y: i32, map: &mut Map) {
+    if !in_bounds(x, y) {
+        return;
+    }
+    let fuel = flammability(x, y, map);
+    let tile = &mut map[x as usize][y as usize];
+    let solid = tile.items.iter().any(|item| {
+        item.blocks == object::Blocks::Full && item.flammability == 0
+    });
+    if !solid {
+        tile.fire = cmp::max(tile.fire, BASE_BURN_TURNS + fuel / 10);
+    }
+}
+
+/// Let fire on the map burn for a turn: spread to flammable neighbours,
+/// destroy what burns, set actors standing in it alight and leave smoke
+/// behind where it goes out.
+pub fn tick(map: &mut Map, actors: &mut [Object], log: &mut log::Messages) {
+    let mut rng = rng::game_rng();
+    let mut spread_to = vec![];
+    for x in 0..map::FLOOR_WIDTH {
+        for y in 0..map::FLOOR_HEIGHT {
+            let tile = &mut map[x as usize][y as usize];
+            if tile.fire <= 0 {
+                if tile.smoke > 0 {
+                    tile.smoke -= 1;
+                }
+                continue;
+            }
+            tile.fire -= 1;
+            if tile.fire == 0 {
+                tile.smoke = SMOKE_TURNS;
+            }
+            tile.items.retain(|item| {
+                item.flammability == 0 ||
+                    rng.gen_range(0, 100) >= item.flammability / 4
+            });
+            for dx in -1..2 {
+                for dy in -1..2 {
+                    spread_to.push((x + dx, y + dy));
+                }
+            }
+        }
+    }
+    for (x, y) in spread_to {
+        if in_bounds(x, y) && !is_burning(x, y, map) &&
+            rng.gen_range(0, 100) < flammability(x, y, map) / 5 {
+                ignite(x, y, map);
+            }
+    }
+
+    for actor in actors.iter_mut() {
+        if actor.fighter.is_some() && is_burning(actor.x, actor.y, map) {
+            status::apply(actor, status::Effect { kind: status::Kind::Burning,
+                                                  turns: 2, potency: 3 },
+                          log);
+        }
+    }
+}
@@ -1,37 +1,149 @@
 use rustc_serialize::json;
 
 use tcod::colors;
-use tcod::input;
+use tcod::input::Event;
 
 use std::io::{Read, Write};
+use std::mem;
+use std::process;
 use std::fs::File;
 use std::error::Error;
 
 use ai;
+use background::{self, Background};
+use command::{self, Command};
 use consts;
+use crafting::{self, Recipe};
+use dialogue::{self, Dialogue};
+use horde;
+use keymap::Keymap;
 use keys;
 use object::{self, actor};
 use map::{self, Map};
 use log;
 use log::MessageLog;
+use noise;
 use object::Object;
+use object::load::{self, ObjectTypes};
+use replay;
+use scheduler;
+use sight::{self, Sight};
+use travel::{self, Destination};
 use ui;
+use ui::backend;
 
 #[derive(RustcEncodable, RustcDecodable)]
 pub struct Game {
     pub map: Map,
     pub log: log::Messages,
+    pub noises: noise::Noises,
+    pub hordes: horde::Hordes,
+    // Floors the player has left behind, in the order they were visited
+    pub past_floors: Vec<Map>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// What the game reads from its data files while it runs, loaded once
+/// when it starts.
+pub struct Data {
+    pub actor_types: ObjectTypes,
+    pub backgrounds: Vec<Background>,
+    pub dialogues: Vec<Dialogue>,
+    pub item_types: ObjectTypes,
+    pub recipes: Vec<Recipe>,
+}
+
+pub fn load_data() -> Result<Data, Box<Error>> {
+    let data = Data {
+        actor_types: try!{ load::load_objects("data/objects/actors.json") },
+        backgrounds: try!{
+            background::load_backgrounds("data/backgrounds.json")
+        },
+        dialogues: try!{ dialogue::load_dialogues("data/dialogue.json") },
+        item_types: try!{ load::load_objects("data/objects/items.json") },
+        recipes: try!{ crafting::load_recipes("data/recipes.json") },
+    };
+    try!{ check_data(&data) };
+    Ok(data)
+}
+
+/// Make sure every item and dialogue the data files mention by name or
+/// type exists, so a typo stops the game from starting instead of
+/// crashing it later.
+fn check_data(data: &Data) -> Result<(), Box<Error>> {
+    let items = &data.item_types;
+    let mut wanted: Vec<(&str, &str)> = vec![];
+    for name in map::FLOOR_ITEMS.iter() {
+        wanted.push((name, "every floor"));
+    }
+    for recipe in &data.recipes {
+        wanted.push((&recipe.output, &recipe.name));
+        for &(ref name, _) in &recipe.ingredients {
+            wanted.push((name, &recipe.name));
+        }
+        for tool in &recipe.tools {
+            wanted.push((tool, &recipe.name));
+        }
+    }
+    for background in &data.backgrounds {
+        for &(ref name, _) in &background.kit {
+            wanted.push((name, &background.name));
+        }
+    }
+    let mut problems: Vec<String> = wanted.into_iter()
+        .filter(|&(name, _)| items.get_class(name).is_none())
+        .map(|(name, user)| format!("no item called {} for {}", name, user))
+        .collect();
+
+    let mut wanted_types = vec![(map::DOOR_TYPE, "every floor")];
+    for class in items.classes().chain(data.actor_types.classes()) {
+        if let Some(ref container) = class.container {
+            for &(ref object_type, _) in &container.loot_table {
+                wanted_types.push((object_type, &class.name));
+            }
+        }
+    }
+    problems.extend(wanted_types.into_iter()
+        .filter(|&(object_type, _)| {
+            items.create_randomizer(object_type).is_none()
+        })
+        .map(|(object_type, user)| {
+            format!("no items of type {} for {}", object_type, user)
+        }));
+
+    for class in data.actor_types.classes() {
+        if let Some(ref wanted) = class.dialogue {
+            if !data.dialogues.iter().any(|d| &d.name == wanted) {
+                problems.push(format!("no dialogue called {} for {}", wanted,
+                                      class.name));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        problems.sort();
+        Err(From::from(problems.join("\n")))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum PlayerAction {
-    TookTurn,
-    DidntTakeTurn,
+    Perform(Command),
+    // Keep walking towards somewhere without further input
+    Travel(Destination),
+    Nothing,
     Exit,
 }
 
-pub fn start_game() {
-    let mut game_ui: ui::Ui = ui::initialize("RustWorld");
+pub fn start_game(kind: backend::Kind) {
+    let mut game_ui: ui::Ui = match ui::initialize("RustWorld", kind) {
+        Ok(game_ui) => game_ui,
+        Err(e) => {
+            eprintln!("Couldn't start the game: {}", e);
+            process::exit(1);
+        }
+    };
 
     ui::main_menu(&mut game_ui);
 }
@@ -43,31 +155,52 @@ pub fn save_game(actors: &[Object], game: &Game) -> Result<(), Box<Error>> {
     Ok(())
 }
 
-pub fn load_game() -> Result<(Vec<Object>, Game), Box<Error>> {
+pub fn read_save() -> Result<String, Box<Error>> {
     let mut json_save_state = String::new();
     let mut file = try! { File::open("savegame") };
     try! { file.read_to_string(&mut json_save_state) };
-    let result = try! { json::decode::<(Vec<Object>, Game)>(&json_save_state) };
+    Ok(json_save_state)
+}
+
+pub fn decode_save(json_save_state: &str)
+                   -> Result<(Vec<Object>, Game), Box<Error>> {
+    let result = try! { json::decode::<(Vec<Object>, Game)>(json_save_state) };
     Ok(result)
 }
 
-pub fn new_game(game_ui: &mut ui::Ui) -> (Vec<Object>, Game) {
+pub fn new_game(name: &str, background: &Background, data: &Data)
+                -> (Vec<Object>, Game) {
 
-    let mut player = Object::new(4, 4, '@', "player", false, colors::WHITE,
+    let mut player = Object::new(4, 4, '@', name, false, colors::WHITE,
                                  object::Blocks::Full, object::Blocks::No);
     player.alive = true;
+    player.description = format!("A former {}, looking for a way out.",
+                                 background.name.to_lowercase());
     player.fighter = Some(actor::Fighter{
-        max_hp: 30, hp: 30, defense: 2, power: 5,
+        max_hp: background.max_hp, hp: background.max_hp, defense: 2, power: 5,
         on_death: actor::DeathCallback::Player,
+        inflicts: None,
     });
-    player.inventory = Some(vec![]);
+    // Ready to act on the very first turn
+    player.energy = scheduler::TURN;
+    let character = background::create_character(background);
+    let max_stamina = 50 + 10 * character.attributes.endurance;
+    player.stamina = Some(actor::Stamina { stamina: max_stamina,
+                                           max_stamina: max_stamina });
+    player.character = Some(character);
     let mut actors = vec![player];
     let mut game = Game {
-        map: map::make_map(&mut actors),
+        map: map::make_map(&mut actors, data),
         log: vec![],
+        noises: vec![],
+        hordes: horde::Hordes::new(),
+        past_floors: vec![],
     };
+    game.hordes.adopt(&mut actors);
 
-    ui::initialize_fov(&game.map, &actors, game_ui);
+    actors[consts::PLAYER].inventory = Some(
+        background::starting_kit(background, &data.item_types,
+                                 &mut game.log));
 
     game.log.info("Meow!");
 
@@ -75,50 +208,151 @@ pub fn new_game(game_ui: &mut ui::Ui) -> (Vec<Object>, Game) {
 
 }
 
-pub fn play_game(actors: &mut Vec<Object>, game: &mut Game, game_ui: &mut ui::Ui) {
+pub fn play_game(actors: &mut Vec<Object>, game: &mut Game, data: &Data,
+                 keymap: &Keymap, game_ui: &mut ui::Ui,
+                 recorder: Result<replay::Recorder, Box<Error>>) {
 
+    let mut recorder = match recorder {
+        Ok(recorder) => Some(recorder),
+        Err(e) => {
+            game.log.alert(format!("Couldn't record a replay: {}", e));
+            None
+        }
+    };
     let mut previous_player_position = (-1, -1);
     let mut key = Default::default();
+    let mut travelling = None;
 
 
     while !game_ui.root.window_closed() {
         let fov_recompute = previous_player_position !=
             (actors[consts::PLAYER].pos());
-        match input::check_for_event(input::MOUSE | input::KEY_PRESS) {
-            Some((_, input::Event::Mouse(m))) => game_ui.mouse = m,
-            Some((_, input::Event::Key(k))) => key = k,
+        let mut clicked = false;
+        match game_ui.root.check_for_event() {
+            Some(Event::Mouse(m)) => {
+                game_ui.mouse = m;
+                clicked = m.lbutton_pressed;
+            }
+            Some(Event::Key(k)) => key = k,
             _ => key = Default::default(),
         }
 
+        if fov_recompute {
+            sight::compute(&mut game.map, actors, &mut game_ui.sight);
+        }
         ui::render_all(game_ui, game, &actors, fov_recompute);
+        game_ui.windows.render(&mut *game_ui.root, &game_ui.theme);
 
         game_ui.root.flush();
 
-        for object in actors.iter_mut() {
-            object.clear(&mut game_ui.con)
-        }
+        ui::clear_actors(game_ui, game, actors);
 
         previous_player_position = actors[consts::PLAYER].pos();
-        let player_action = keys::handle_keys(key, game_ui, game, actors);
-        if player_action == PlayerAction::Exit {
-            //# TODO: Catch and handle save game errors
-            save_game(actors, game).unwrap();
-            break
-        }
-
-        if actors[consts::PLAYER].alive &&
-            player_action != PlayerAction::DidntTakeTurn {
-                for id in 0..actors.len() {
-                    if actors[id].ai.is_some() {
-                        ai::take_turn(id, game, actors, &game_ui.fov);
+        let player_action = match travelling {
+            // Any key or click takes back control
+            Some(_) if key.pressed || clicked => {
+                game.log.info("You stop.");
+                travelling = None;
+                PlayerAction::Nothing
+            }
+            Some(destination) => {
+                match travel::next_command(destination, game, actors,
+                                           &game_ui.sight) {
+                    Some(command) => PlayerAction::Perform(command),
+                    None => {
+                        travelling = None;
+                        PlayerAction::Nothing
+                    }
+                }
+            }
+            // Open windows take the keys until they are closed
+            None if !game_ui.windows.is_empty() => {
+                let answered = if key.pressed {
+                    game_ui.windows.handle_key(key)
+                } else {
+                    None
+                };
+                match answered {
+                    Some((purpose, outcome)) =>
+                        keys::handle_window(purpose, outcome, keymap,
+                                            game_ui, game, actors),
+                    None => PlayerAction::Nothing,
+                }
+            }
+            None if clicked => keys::handle_click(game_ui, game, actors),
+            None => keys::handle_keys(key, keymap, game_ui, game, data,
+                                      actors),
+        };
+        match player_action {
+            PlayerAction::Exit => {
+                //# TODO: Catch and handle save game errors
+                save_game(actors, game).unwrap();
+                break
+            }
+            PlayerAction::Perform(command) => {
+                let recorded = recorder.as_mut()
+                    .map_or(Ok(()), |recorder| recorder.record(&command));
+                if let Err(e) = recorded {
+                    game.log.alert(format!("Stopped recording: {}", e));
+                    recorder = None;
+                }
+                let floor = game.past_floors.len();
+                if let Some(noises) = perform(&command, game, data, actors,
+                                              &mut game_ui.sight) {
+                    previous_player_position = (-1, -1);
+                    if game.past_floors.len() != floor {
+                        ui::initialize_fov(&game.map, &actors, game_ui);
+                        travelling = None;
+                    }
+                    let heard = noise::loudest_heard(&actors[consts::PLAYER],
+                                                     &noises);
+                    if travelling.is_some() && heard.is_some() {
+                        game.log.alert("You hear something and stop.");
+                        travelling = None;
                     }
                 }
             }
+            PlayerAction::Travel(destination) => {
+                travelling = Some(destination);
+            }
+            PlayerAction::Nothing => {}
+        }
+    }
+}
 
+/// Carry out a command for the player and, if it took them a turn, let
+/// the rest of the world catch up. Returns the noises everything else
+/// made in the meantime, or None if no time passed.
+pub fn perform(command: &Command, game: &mut Game, data: &Data,
+               actors: &mut Vec<Object>, sight: &mut Sight)
+               -> Option<noise::Noises> {
+    let action = match command::execute(command, game, data, actors, sight) {
+        Some(action) => action,
+        None => return None,
+    };
+    if !actors[consts::PLAYER].alive {
+        return None;
     }
+    actors[consts::PLAYER].energy -=
+        scheduler::player_cost(action, &actors[consts::PLAYER]);
+    actor::spend_stamina(action, &mut actors[consts::PLAYER], &mut game.log);
+    let player_noises = game.noises.len();
+    scheduler::advance(game, actors, &sight.fov);
+    let noises = game.noises.split_off(player_noises);
+    game.noises.clear();
+    horde::migrate(game, actors, &sight.fov);
+
+    // Doors, fire and smoke change what can be seen even when the player
+    // stands still
+    sight::refresh(&game.map, &actors, sight);
+    Some(noises)
 }
 
-pub fn next_level(game_ui: &mut ui::Ui, actors: &mut Vec<Object>, game: &mut Game) {
-    game.map = map::make_map(actors);
-    ui::initialize_fov(&game.map, &actors, game_ui);
+/// Leave for a freshly made floor, keeping the old one for the overview
+/// map. Everyone but the player stays behind.
+pub fn next_level(actors: &mut Vec<Object>, game: &mut Game, data: &Data) {
+    actors.truncate(consts::PLAYER + 1);
+    let floor = mem::replace(&mut game.map, map::make_map(actors, data));
+    game.past_floors.push(floor);
+    game.hordes.adopt(actors);
 }
@@ -0,0 +1,202 @@
+use rand::Rng;
+
+use tcod::map::Map as FovMap;
+
+use consts;
+use game::Game;
+use log::MessageLog;
+use map::{self, Map};
+use object::{self, Object};
+use util::rng;
+
+/// Turns a horde mills about before heading somewhere new.
+const MIN_WANDER_TURNS: i32 = 20;
+const MAX_WANDER_TURNS: i32 = 40;
+/// One in this many chance each turn that a quiet horde leaves the floor,
+/// or that one from elsewhere in the building shows up.
+const LEAVE_ODDS: u32 = 400;
+const ARRIVE_ODDS: u32 = 400;
+/// How far from where it arrives a horde can spread out.
+const ARRIVAL_SPREAD: i32 = 2;
+
+/// A group of zombies that roam the building together. Members only look
+/// at their horde's shared goal, so keeping dozens of them together costs
+/// one update per horde instead of one for every pair of zombies.
+#[derive(Clone, Copy, Debug, RustcEncodable, RustcDecodable)]
+pub struct Horde {
+    pub id: usize,
+    // Where the members are heading
+    pub goal: (i32, i32),
+    // Turns left before picking somewhere new to go
+    pub wander_turns: i32,
+    // Set when a member has spotted or heard something worth chasing
+    pub alerted: bool,
+}
+
+#[derive(Debug, RustcEncodable, RustcDecodable)]
+pub struct Hordes {
+    next_id: usize,
+    // Hordes on the current floor
+    pub here: Vec<Horde>,
+    // Hordes roaming other floors, along with their members
+    pub elsewhere: Vec<(Horde, Vec<Object>)>,
+}
+
+impl Hordes {
+    pub fn new() -> Self {
+        Hordes {
+            next_id: 0,
+            here: vec![],
+            elsewhere: vec![],
+        }
+    }
+
+    /// Take charge of the hordes placed on a freshly made floor. Map
+    /// generation numbers them from zero, so they get fresh ids here.
+    pub fn adopt(&mut self, actors: &mut [Object]) {
+        self.here.clear();
+        let mut renamed: Vec<(usize, usize)> = vec![];
+        for actor in actors.iter_mut() {
+            let local_id = match actor.horde {
+                Some(local_id) => local_id,
+                None => continue,
+            };
+            let id = match renamed.iter().find(|&&(old, _)| old == local_id) {
+                Some(&(_, id)) => id,
+                None => {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    renamed.push((local_id, id));
+                    self.here.push(Horde {
+                        id: id,
+                        goal: actor.pos(),
+                        wander_turns: 0,
+                        alerted: false,
+                    });
+                    id
+                }
+            };
+            actor.horde = Some(id);
+        }
+    }
+
+    fn get_mut(&mut self, id: usize) -> Option<&mut Horde> {
+        self.here.iter_mut().find(|horde| horde.id == id)
+    }
+}
+
+/// Where the horde `member` belongs to is heading, if it is in one.
+pub fn goal(hordes: &Hordes, member: &Object) -> Option<(i32, i32)> {
+    member.horde.and_then(|id| {
+        hordes.here.iter().find(|horde| horde.id == id)
+    }).map(|horde| horde.goal)
+}
+
+/// Whether the horde `member` belongs to is after something.
+pub fn is_alerted(hordes: &Hordes, member: &Object) -> bool {
+    member.horde.and_then(|id| {
+        hordes.here.iter().find(|horde| horde.id == id)
+    }).map_or(false, |horde| horde.alerted)
+}
+
+/// Send the whole horde of `member` towards (x, y).
+pub fn alert(hordes: &mut Hordes, member: &Object, x: i32, y: i32) {
+    if let Some(horde) = member.horde.and_then(|id| hordes.get_mut(id)) {
+        horde.goal = (x, y);
+        horde.alerted = true;
+        horde.wander_turns = MIN_WANDER_TURNS;
+    }
+}
+
+/// A random spot on the map nothing is standing in.
+fn random_open_tile(map: &Map, actors: &[Object]) -> Option<(i32, i32)> {
+    for _ in 0..100 {
+        let x = rng::game_rng().gen_range(1, map::FLOOR_WIDTH - 1);
+        let y = rng::game_rng().gen_range(1, map::FLOOR_HEIGHT - 1);
+        if map::is_blocked(x, y, map, actors) == object::Blocks::No {
+            return Some((x, y));
+        }
+    }
+    None
+}
+
+/// Let a turn pass for every horde: forget the ones with nobody left and
+/// pick new places to wander to for those that have lost interest.
+pub fn tick(hordes: &mut Hordes, map: &Map, actors: &[Object]) {
+    hordes.here.retain(|horde| {
+        actors.iter().any(|actor| actor.alive && actor.horde == Some(horde.id))
+    });
+    for horde in &mut hordes.here {
+        horde.wander_turns -= 1;
+        if horde.wander_turns <= 0 {
+            if let Some(goal) = random_open_tile(map, actors) {
+                horde.goal = goal;
+            }
+            horde.wander_turns = rng::game_rng().gen_range(
+                MIN_WANDER_TURNS, MAX_WANDER_TURNS + 1);
+            horde.alerted = false;
+        }
+    }
+}
+
+/// Every so often a horde the player can't see wanders off to another
+/// floor, or one from elsewhere in the building turns up out of sight.
+pub fn migrate(game: &mut Game, actors: &mut Vec<Object>, fov_map: &FovMap) {
+    let leaving = game.hordes.here.iter().position(|horde| {
+        !horde.alerted &&
+            rng::game_rng().gen_range(0, LEAVE_ODDS) == 0 &&
+            !actors.iter().any(|actor| {
+                actor.horde == Some(horde.id) &&
+                    fov_map.is_in_fov(actor.x, actor.y)
+            })
+    });
+    if let Some(horde_id) = leaving {
+        let horde = game.hordes.here.remove(horde_id);
+        let mut members = vec![];
+        for id in (0..actors.len()).rev() {
+            if id != consts::PLAYER && actors[id].alive &&
+                actors[id].horde == Some(horde.id) {
+                    members.push(actors.remove(id));
+                }
+        }
+        game.hordes.elsewhere.push((horde, members));
+    }
+
+    if game.hordes.elsewhere.is_empty() ||
+        rng::game_rng().gen_range(0, ARRIVE_ODDS) != 0 {
+            return;
+        }
+    let arrival = random_open_tile(&game.map, actors);
+    let (x, y) = match arrival {
+        Some((x, y)) if !fov_map.is_in_fov(x, y) => (x, y),
+        _ => return,
+    };
+    let choice = rng::game_rng().gen_range(0, game.hordes.elsewhere.len());
+    let (mut horde, members) = game.hordes.elsewhere.remove(choice);
+    for mut member in members {
+        if let Some((member_x, member_y)) =
+            open_tile_near(x, y, &game.map, actors) {
+                member.set_pos(member_x, member_y);
+                actors.push(member);
+            }
+    }
+    horde.goal = (x, y);
+    horde.wander_turns = MIN_WANDER_TURNS;
+    game.hordes.here.push(horde);
+    game.log.alert("You hear shuffling feet somewhere in the building.");
+}
+
+fn open_tile_near(x: i32, y: i32, map: &Map, actors: &[Object])
+                  -> Option<(i32, i32)> {
+    for tile_x in x - ARRIVAL_SPREAD..x + ARRIVAL_SPREAD + 1 {
+        for tile_y in y - ARRIVAL_SPREAD..y + ARRIVAL_SPREAD + 1 {
+            if tile_x > 0 && tile_y > 0 && tile_x < map::FLOOR_WIDTH - 1 &&
+                tile_y < map::FLOOR_HEIGHT - 1 &&
+                map::is_blocked(tile_x, tile_y, map, actors) ==
+                object::Blocks::No {
+                    return Some((tile_x, tile_y));
+                }
+        }
+    }
+    None
+}
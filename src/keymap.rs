@@ -0,0 +1,383 @@
+use rustc_serialize::json;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use tcod::input::{Key, KeyCode};
+
+use log::{self, MessageLog};
+
+/// The keymap shipped with the game.
+pub const DEFAULT_KEYMAP: &'static str = "data/keymap.json";
+/// The player's own bindings, laid over the defaults. Only the actions
+/// they want to change need to be listed.
+pub const USER_KEYMAP: &'static str = "keymap.json";
+
+/// Everything a key can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable)]
+pub enum KeyAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    MoveUpLeft,
+    MoveUpRight,
+    MoveDownLeft,
+    MoveDownRight,
+    Wait,
+    Explore,
+    Travel,
+    ToggleSneak,
+    ToggleSprint,
+    PickUp,
+    UseItem,
+    Drop,
+    Craft,
+    Search,
+    Talk,
+    Fire,
+    ExamineItem,
+    ExamineTile,
+    Overview,
+    CharacterScreen,
+    MessageLog,
+    Stairs,
+    Help,
+    Exit,
+}
+
+impl KeyAction {
+    pub fn description(self) -> &'static str {
+        match self {
+            KeyAction::MoveUp => "Move up",
+            KeyAction::MoveDown => "Move down",
+            KeyAction::MoveLeft => "Move left",
+            KeyAction::MoveRight => "Move right",
+            KeyAction::MoveUpLeft => "Move up and left",
+            KeyAction::MoveUpRight => "Move up and right",
+            KeyAction::MoveDownLeft => "Move down and left",
+            KeyAction::MoveDownRight => "Move down and right",
+            KeyAction::Wait => "Wait a turn",
+            KeyAction::Explore => "Explore until something turns up",
+            KeyAction::Travel => "Travel to somewhere you have been",
+            KeyAction::ToggleSneak => "Sneak / walk",
+            KeyAction::ToggleSprint => "Sprint / walk",
+            KeyAction::PickUp => "Pick up",
+            KeyAction::UseItem => "Use or equip an item",
+            KeyAction::Drop => "Drop an item",
+            KeyAction::Craft => "Craft",
+            KeyAction::Search => "Search a container",
+            KeyAction::Talk => "Talk",
+            KeyAction::Fire => "Fire your weapon",
+            KeyAction::ExamineItem => "Examine an item",
+            KeyAction::ExamineTile => "Look around the map",
+            KeyAction::Overview => "Overview map of the floors explored",
+            KeyAction::CharacterScreen => "Character screen",
+            KeyAction::MessageLog => "Message history",
+            KeyAction::Stairs => "Take the stairs",
+            KeyAction::Help => "Show this help",
+            KeyAction::Exit => "Save and quit",
+        }
+    }
+
+    /// Which way a movement action points.
+    pub fn direction(self) -> Option<(i32, i32)> {
+        match self {
+            KeyAction::MoveUp => Some((0, -1)),
+            KeyAction::MoveDown => Some((0, 1)),
+            KeyAction::MoveLeft => Some((-1, 0)),
+            KeyAction::MoveRight => Some((1, 0)),
+            KeyAction::MoveUpLeft => Some((-1, -1)),
+            KeyAction::MoveUpRight => Some((1, -1)),
+            KeyAction::MoveDownLeft => Some((-1, 1)),
+            KeyAction::MoveDownRight => Some((1, 1)),
+            _ => None,
+        }
+    }
+}
+
+/// Keys that don't type a character, by the name used in keymap files.
+const NAMED_KEYS: &'static [(&'static str, KeyCode)] = &[
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Insert", KeyCode::Insert),
+    ("Delete", KeyCode::Delete),
+    ("Space", KeyCode::Spacebar),
+    ("Enter", KeyCode::Enter),
+    ("Escape", KeyCode::Escape),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("NumPad0", KeyCode::NumPad0),
+    ("NumPad1", KeyCode::NumPad1),
+    ("NumPad2", KeyCode::NumPad2),
+    ("NumPad3", KeyCode::NumPad3),
+    ("NumPad4", KeyCode::NumPad4),
+    ("NumPad5", KeyCode::NumPad5),
+    ("NumPad6", KeyCode::NumPad6),
+    ("NumPad7", KeyCode::NumPad7),
+    ("NumPad8", KeyCode::NumPad8),
+    ("NumPad9", KeyCode::NumPad9),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Trigger {
+    Code(KeyCode),
+    // A typed character. Shift is already part of it, e.g. 'C' or '>'
+    Char(char),
+}
+
+/// A key together with the modifiers that have to be held with it,
+/// parsed from names like "k", "NumPad8" or "Ctrl+q".
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyChord {
+    trigger: Trigger,
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+}
+
+impl KeyChord {
+    pub fn parse(name: &str) -> Result<KeyChord, String> {
+        // "+" on its own, or at the end after another "+", is the plus key
+        let (modifiers, key) = if name == "+" {
+            ("", "+")
+        } else if name.ends_with("++") {
+            (&name[..name.len() - 2], "+")
+        } else {
+            match name.rfind('+') {
+                Some(split) => (&name[..split], &name[split + 1..]),
+                None => ("", name),
+            }
+        };
+
+        let mut chord = KeyChord {
+            trigger: Trigger::Char(' '),
+            ctrl: false,
+            alt: false,
+            shift: false,
+        };
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier {
+                "Ctrl" => chord.ctrl = true,
+                "Alt" => chord.alt = true,
+                "Shift" => chord.shift = true,
+                _ => return Err(format!("unknown modifier {} in {}",
+                                        modifier, name)),
+            }
+        }
+
+        let mut chars = key.chars();
+        chord.trigger = match (chars.next(), chars.next()) {
+            (Some(c), None) => Trigger::Char(c),
+            _ => match NAMED_KEYS.iter().find(|&&(n, _)| n == key) {
+                Some(&(_, code)) => Trigger::Code(code),
+                None => return Err(format!("unknown key {}", name)),
+            },
+        };
+        Ok(chord)
+    }
+
+    pub fn matches(&self, key: Key) -> bool {
+        key.ctrl == self.ctrl && key.alt == self.alt && match self.trigger {
+            Trigger::Code(code) => key.code == code && key.shift == self.shift,
+            Trigger::Char(c) => key.printable == c,
+        }
+    }
+}
+
+/// Keys for one action, as written in a keymap file.
+#[derive(Clone, Debug, RustcDecodable)]
+pub struct Binding {
+    pub action: KeyAction,
+    pub keys: Vec<String>,
+}
+
+#[derive(Debug, RustcDecodable)]
+struct JsonKeymap {
+    bindings: Vec<Binding>,
+}
+
+pub struct Keymap {
+    bindings: Vec<Binding>,
+    chords: Vec<(KeyChord, KeyAction)>,
+}
+
+impl Keymap {
+    pub fn new(bindings: Vec<Binding>) -> Result<Keymap, Box<Error>> {
+        let mut chords = vec![];
+        for binding in &bindings {
+            for key in &binding.keys {
+                chords.push((try!{ KeyChord::parse(key) }, binding.action));
+            }
+        }
+        Ok(Keymap { bindings: bindings, chords: chords })
+    }
+
+    pub fn action_for(&self, key: Key) -> Option<KeyAction> {
+        self.chords.iter().find(|&&(chord, _)| chord.matches(key))
+            .map(|&(_, action)| action)
+    }
+
+    /// Every action with the keys bound to it, for the help screen.
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+}
+
+fn read_bindings(filename: &str) -> Result<Vec<Binding>, Box<Error>> {
+    let mut json = String::new();
+    let mut file = try!{ File::open(filename) };
+    try!{ file.read_to_string(&mut json) };
+    let keymap: JsonKeymap = try!{ json::decode(&json) };
+    Ok(keymap.bindings)
+}
+
+/// `defaults` with each action in `overrides` bound to the keys given
+/// there instead.
+fn merge_bindings(defaults: &[Binding], overrides: Vec<Binding>)
+                  -> Vec<Binding> {
+    let mut bindings = defaults.to_vec();
+    for binding in overrides {
+        match bindings.iter().position(|b| b.action == binding.action) {
+            Some(id) => bindings[id] = binding,
+            None => bindings.push(binding),
+        }
+    }
+    bindings
+}
+
+/// The default keymap with the player's own bindings applied. Problems
+/// with the player's file are reported and the defaults used instead.
+pub fn load_keymap(log: &mut log::Messages) -> Result<Keymap, Box<Error>> {
+    let defaults = try!{ read_bindings(DEFAULT_KEYMAP) };
+    if !Path::new(USER_KEYMAP).exists() {
+        return Keymap::new(defaults);
+    }
+
+    let overrides = read_bindings(USER_KEYMAP).and_then(|overrides| {
+        Keymap::new(merge_bindings(&defaults, overrides))
+    });
+    match overrides {
+        Ok(keymap) => Ok(keymap),
+        Err(e) => {
+            log.alert(format!("Couldn't use {}: {}", USER_KEYMAP, e));
+            Keymap::new(defaults)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tcod::input::{Key, KeyCode};
+
+    use super::*;
+
+    fn typed(c: char) -> Key {
+        Key { code: KeyCode::Char, printable: c, pressed: true,
+              ..Default::default() }
+    }
+
+    fn pressed(code: KeyCode) -> Key {
+        Key { code: code, pressed: true, ..Default::default() }
+    }
+
+    fn binding(action: KeyAction, keys: &[&str]) -> Binding {
+        Binding {
+            action: action,
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parse_plus() {
+        let plus = KeyChord::parse("+").unwrap();
+        assert_eq!(plus.trigger, Trigger::Char('+'));
+        assert!(!plus.ctrl);
+
+        let ctrl_plus = KeyChord::parse("Ctrl++").unwrap();
+        assert_eq!(ctrl_plus.trigger, Trigger::Char('+'));
+        assert!(ctrl_plus.ctrl);
+        assert!(!ctrl_plus.alt);
+    }
+
+    #[test]
+    fn parse_named_keys_and_modifiers() {
+        let chord = KeyChord::parse("Alt+NumPad8").unwrap();
+        assert_eq!(chord.trigger, Trigger::Code(KeyCode::NumPad8));
+        assert!(chord.alt);
+        assert!(!chord.ctrl);
+    }
+
+    #[test]
+    fn parse_unknown_names() {
+        assert!(KeyChord::parse("NumPad10").is_err());
+        assert!(KeyChord::parse("Hyper+a").is_err());
+        assert!(KeyChord::parse("").is_err());
+    }
+
+    #[test]
+    fn shift_is_ignored_for_characters() {
+        let chord = KeyChord::parse("Shift+a").unwrap();
+        assert!(chord.matches(typed('a')));
+        let mut shifted = typed('a');
+        shifted.shift = true;
+        assert!(chord.matches(shifted));
+    }
+
+    #[test]
+    fn shift_counts_for_named_keys() {
+        let chord = KeyChord::parse("Shift+Up").unwrap();
+        assert!(!chord.matches(pressed(KeyCode::Up)));
+        let mut shifted = pressed(KeyCode::Up);
+        shifted.shift = true;
+        assert!(chord.matches(shifted));
+    }
+
+    #[test]
+    fn matches_needs_the_same_modifiers() {
+        let chord = KeyChord::parse("Ctrl+q").unwrap();
+        assert!(!chord.matches(typed('q')));
+        let mut ctrl_q = typed('q');
+        ctrl_q.ctrl = true;
+        assert!(chord.matches(ctrl_q));
+        ctrl_q.alt = true;
+        assert!(!chord.matches(ctrl_q));
+    }
+
+    #[test]
+    fn overrides_replace_and_add_bindings() {
+        let defaults = vec![binding(KeyAction::MoveUp, &["Up", "k"]),
+                            binding(KeyAction::Wait, &["Space"])];
+        let overrides = vec![binding(KeyAction::MoveUp, &["w"]),
+                             binding(KeyAction::PickUp, &["g"])];
+        let keymap = Keymap::new(merge_bindings(&defaults, overrides))
+            .unwrap();
+
+        assert_eq!(keymap.action_for(typed('w')), Some(KeyAction::MoveUp));
+        assert_eq!(keymap.action_for(typed('k')), None);
+        assert_eq!(keymap.action_for(pressed(KeyCode::Up)), None);
+        assert_eq!(keymap.action_for(pressed(KeyCode::Spacebar)),
+                   Some(KeyAction::Wait));
+        assert_eq!(keymap.action_for(typed('g')), Some(KeyAction::PickUp));
+        assert_eq!(keymap.bindings().len(), 3);
+    }
+}
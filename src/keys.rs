@@ -1,26 +1,40 @@
-use tcod::input::{Key, KeyCode};
+use tcod::input::Key;
 
 use game::{
     self,
+    Data,
     Game,
 };
 
+use command::Command;
 use game::PlayerAction;
 use game::PlayerAction::*;
 
-use ui::{Ui, inventory_menu};
+use ui::{Ui, PickUpChoice, character_window, examine_window, help_window,
+         inventory_window, log_window, look, overview, pick_up_menu,
+         travel_menu};
+use ui::window::{Outcome, Purpose, Window};
 
 use consts;
-use log::MessageLog;
+use keymap::KeyAction;
+use keymap::Keymap;
 use object::Object;
 use object::actor;
+use travel::{self, Destination};
 
-pub fn handle_keys(key: Key, game_ui: &mut Ui, game: &mut Game,
-               actors: &mut Vec<Object>) -> PlayerAction {
+/// Turn a key press into a command for the player, asking for whatever
+/// else it needs to know along the way. Keys that only show information
+/// are dealt with here and come back as `Nothing`.
+pub fn handle_keys(key: Key, keymap: &Keymap, game_ui: &mut Ui,
+                   game: &mut Game, data: &Data, actors: &mut Vec<Object>)
+                   -> PlayerAction {
     let player_alive = actors[consts::PLAYER].alive;
-    match (key, player_alive) {
-        // Exit: Ctrl+q
-        (Key { printable: 'q', ctrl: true, .. }, _) => Exit,
+    match (keymap.action_for(key), player_alive) {
+        (Some(KeyAction::Exit), _) => {
+            game_ui.windows.push(Window::confirm("Save and quit?",
+                                                 Purpose::Quit));
+            Nothing
+        }
         //*************************************************
         // Movement keys
         //*************************************************
@@ -28,81 +42,76 @@ pub fn handle_keys(key: Key, game_ui: &mut Ui, game: &mut Game,
         ///////////////////////////////////////////////////
         // Up
         ///////////////////////////////////////////////////
-        (Key { code: KeyCode::Up, ctrl: false, alt: false, .. }, true) |
-        (Key { code: KeyCode::NumPad8, ..}, true) |
-        (Key { printable: 'k', ..}, true) => {
-            actor::player_move_or_attack(0, -1, game, actors);
-            TookTurn
+        (Some(KeyAction::MoveUp), true) => {
+            step(0, -1, data, game_ui, game, actors)
         }
         ///////////////////////////////////////////////////
         // Down
         ///////////////////////////////////////////////////
-        (Key { code: KeyCode::Down, ctrl: false, alt: false,.. }, true) |
-        (Key { code: KeyCode::NumPad2, ctrl: false, alt: false, ..}, true) |
-        (Key { printable: 'j', ..}, true) => {
-            actor::player_move_or_attack(0, 1, game, actors);
-            TookTurn
+        (Some(KeyAction::MoveDown), true) => {
+            step(0, 1, data, game_ui, game, actors)
         }
         ///////////////////////////////////////////////////
         // Left
         ///////////////////////////////////////////////////
-        (Key { code: KeyCode::Left, ctrl: false, alt: false, .. }, true) |
-        (Key { code: KeyCode::NumPad4, ctrl: false, alt: false, ..}, true) |
-        (Key { printable: 'h', ctrl: false, alt: false, ..}, true) => {
-            actor::player_move_or_attack(-1, 0, game, actors);
-            TookTurn
+        (Some(KeyAction::MoveLeft), true) => {
+            step(-1, 0, data, game_ui, game, actors)
         }
         ///////////////////////////////////////////////////
         // Right
         ///////////////////////////////////////////////////
-        (Key { code: KeyCode::Right, ctrl: false, alt: false, .. }, true) |
-        (Key { code: KeyCode::NumPad6, ctrl: false, alt: false, ..}, true) |
-        (Key { printable: 'l', ..}, true) => {
-            actor::player_move_or_attack(1, 0, game, actors);
-            TookTurn
+        (Some(KeyAction::MoveRight), true) => {
+            step(1, 0, data, game_ui, game, actors)
         }
         ///////////////////////////////////////////////////
         // Up Left
         ///////////////////////////////////////////////////
-        (Key { code: KeyCode::Home, .. }, true) |
-        (Key { code: KeyCode::NumPad7, ..}, true) |
-        (Key { printable: 'y', ..}, true) => {
-            actor::player_move_or_attack(-1, -1, game, actors);
-            TookTurn
+        (Some(KeyAction::MoveUpLeft), true) => {
+            step(-1, -1, data, game_ui, game, actors)
         }
         ///////////////////////////////////////////////////
         // Up Right
         ///////////////////////////////////////////////////
-        (Key { code: KeyCode::PageUp, .. }, true) |
-        (Key { code: KeyCode::NumPad9, ..}, true) |
-        (Key { printable: 'u', ..}, true) => {
-            actor::player_move_or_attack(1, -1, game, actors);
-            TookTurn
+        (Some(KeyAction::MoveUpRight), true) => {
+            step(1, -1, data, game_ui, game, actors)
         }
         ///////////////////////////////////////////////////
         // Down Left
         ///////////////////////////////////////////////////
-        (Key { code: KeyCode::End, .. }, true) |
-        (Key { code: KeyCode::NumPad1, ..}, true) |
-        (Key { printable: 'b', ..}, true) => {
-            actor::player_move_or_attack(-1, 1, game, actors);
-            TookTurn
+        (Some(KeyAction::MoveDownLeft), true) => {
+            step(-1, 1, data, game_ui, game, actors)
         }
         ///////////////////////////////////////////////////
         // Down Right
         ///////////////////////////////////////////////////
-        (Key { code: KeyCode::PageDown, .. }, true) |
-        (Key { code: KeyCode::NumPad3, ..}, true) |
-        (Key { printable: 'n', ..}, true) => {
-            actor::player_move_or_attack(1, 1, game, actors);
-            TookTurn
+        (Some(KeyAction::MoveDownRight), true) => {
+            step(1, 1, data, game_ui, game, actors)
         }
         ///////////////////////////////////////////////////
         // Wait (Don't move)
         ///////////////////////////////////////////////////
-        (Key { code: KeyCode::Spacebar, ..}, true) |
-        (Key { code: KeyCode::NumPad5, ..}, true) => {
-            TookTurn
+        (Some(KeyAction::Wait), true) => Perform(Command::Wait),
+        ///////////////////////////////////////////////////
+        // Travel
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::Explore), true) => Travel(Destination::Unexplored),
+        (Some(KeyAction::Travel), true) => {
+            let (x, y) = actors[consts::PLAYER].pos();
+            let features = travel::features(&game.map, x, y);
+            match travel_menu(&features, &actors[consts::PLAYER], game_ui) {
+                Some(id) => Travel(Destination::Tile(features[id].x,
+                                                     features[id].y)),
+                None => Nothing,
+            }
+        }
+        ///////////////////////////////////////////////////
+        // Movement modes
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::ToggleSneak), true) => {
+            Perform(Command::ToggleMoveMode(actor::MoveMode::Sneak))
+        }
+        (Some(KeyAction::ToggleSprint), true) => {
+            Perform(Command::ToggleMoveMode(actor::MoveMode::Sprint))
         }
         //*************************************************
         // End movement keys
@@ -111,60 +120,168 @@ pub fn handle_keys(key: Key, game_ui: &mut Ui, game: &mut Game,
         ///////////////////////////////////////////////////
         // Pick up
         ///////////////////////////////////////////////////
-        (Key { printable: ',', ..}, true) => {
-            let (player_x, player_y) = actors[consts::PLAYER].pos();
-            if let Some(ref mut player_inventory) =
-                actors[consts::PLAYER].inventory {
-                    actor::pick_up_items(player_x, player_y, player_inventory,
-                                         game);
-                };
-            DidntTakeTurn
-        }
-        (Key { printable: 'i', ctrl: false, alt: false, .. }, true) => {
-            if let Some(ref mut player_inventory) =
-                actors[consts::PLAYER].inventory {
-                    let inventory_index = inventory_menu(
-                        &player_inventory,
-                        "Press the key next to an item to use it, \
-                         or any other to cancel.\n",
-                        &mut game_ui.root);
-                    if let Some(inventory_index) = inventory_index {
-                        actor::use_item(game_ui, game, inventory_index,
-                                        player_inventory);
-                    }
-                }
-            DidntTakeTurn
-        }
-        (Key { printable: 'd', ctrl: false, alt: false, .. }, true) => {
-            let (x, y) = actors[consts::PLAYER].pos();
-            if let Some(ref mut player_inventory) =
-                actors[consts::PLAYER].inventory {
-                    let inventory_index = inventory_menu(
-                        &player_inventory,
-                        "Press the key next to an item to drop it, \
-                         or any other to cancel.\n",
-                        &mut game_ui.root);
-                    if let Some(inventory_index) = inventory_index {
-                        actor::drop_item(x, y, inventory_index, game,
-                                         player_inventory);
-                    }
-                }
-            DidntTakeTurn
-        }
-        (Key { printable: '>', ctrl: false, alt: false, .. }, true) => {
-            // let player_on_stairs = game.map.iter().any(
-            //     |object| {
-            //         object.pos() == actors[consts::PLAYER].pos() &&
-            //             object.name == "stairs up"
-            //     });
+        (Some(KeyAction::PickUp), true) => {
             let (player_x, player_y) = actors[consts::PLAYER].pos();
-            let player_on_stairs = game.map[player_x as usize][player_y as usize]
-                .items.iter().any(|object| object.name == "stairs up");
-            if player_on_stairs {
-                game.log.success("Go upstairs!");
+            let items = &game.map[player_x as usize][player_y as usize].items;
+            let num_items = items.iter().filter(|item| item.can_pick_up)
+                .count();
+            let choice = match num_items {
+                0 => None,
+                1 => Some(PickUpChoice::Everything),
+                _ => pick_up_menu(items, game_ui),
+            };
+            match choice {
+                Some(PickUpChoice::Everything) =>
+                    Perform(Command::PickUp(None)),
+                Some(PickUpChoice::Item(item_id)) =>
+                    Perform(Command::PickUp(Some(item_id))),
+                None => Nothing,
             }
-            DidntTakeTurn
         }
-        _ => DidntTakeTurn,
+        (Some(KeyAction::UseItem), true) => {
+            open_inventory("Press the key next to an item to use, equip or \
+                            unequip it, or any other to cancel.\n",
+                           Purpose::UseItem, game_ui, actors);
+            Nothing
+        }
+        ///////////////////////////////////////////////////
+        // Craft
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::Craft), true) => {
+            perform(actor::choose_recipe(&data.recipes, game_ui, game,
+                                         actors))
+        }
+        ///////////////////////////////////////////////////
+        // Search a nearby container
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::Search), true) => {
+            perform(actor::choose_search(game_ui, game, actors))
+        }
+        ///////////////////////////////////////////////////
+        // Talk to a survivor
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::Talk), true) => {
+            perform(actor::choose_talk_partner(&data.dialogues, game_ui, game,
+                                               actors))
+        }
+        ///////////////////////////////////////////////////
+        // Examine an inventory item
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::ExamineItem), true) => {
+            open_inventory("Press the key next to an item to examine it, \
+                            or any other to stop.\n",
+                           Purpose::ExamineItem, game_ui, actors);
+            Nothing
+        }
+        ///////////////////////////////////////////////////
+        // Map of everywhere explored so far
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::Overview), _) => {
+            overview(game, actors, &game_ui.layout, &game_ui.theme,
+                     &mut *game_ui.root);
+            Nothing
+        }
+        ///////////////////////////////////////////////////
+        // Character screen
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::CharacterScreen), _) => {
+            game_ui.windows.push(character_window(&actors[consts::PLAYER]));
+            Nothing
+        }
+        ///////////////////////////////////////////////////
+        // Message history
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::MessageLog), _) => {
+            game_ui.windows.push(log_window(&game.log, &game_ui.theme));
+            Nothing
+        }
+        ///////////////////////////////////////////////////
+        // Examine something on the map
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::ExamineTile), _) => {
+            look(keymap, game, actors, game_ui);
+            Nothing
+        }
+        (Some(KeyAction::Drop), true) => {
+            open_inventory("Press the key next to an item to drop it, \
+                            or any other to cancel.\n",
+                           Purpose::DropItem, game_ui, actors);
+            Nothing
+        }
+        ///////////////////////////////////////////////////
+        // Fire main hand ranged weapon
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::Fire), true) => {
+            perform(actor::choose_fire(keymap, game_ui, game, actors))
+        }
+        (Some(KeyAction::Stairs), true) => Perform(Command::Stairs),
+        ///////////////////////////////////////////////////
+        // Help
+        ///////////////////////////////////////////////////
+        (Some(KeyAction::Help), _) => {
+            game_ui.windows.push(help_window(keymap));
+            Nothing
+        }
+        _ => Nothing,
+    }
+}
+
+fn open_inventory(header: &str, purpose: Purpose, game_ui: &mut Ui,
+                  actors: &[Object]) {
+    if let Some(ref inventory) = actors[consts::PLAYER].inventory {
+        let mut window = inventory_window(inventory, header, purpose);
+        // Look over one item after another
+        window.keep_open = purpose == Purpose::ExamineItem;
+        game_ui.windows.push(window);
     }
 }
+
+/// Act on the answer from a window opened by `handle_keys`.
+pub fn handle_window(purpose: Purpose, outcome: Outcome, keymap: &Keymap,
+                     game_ui: &mut Ui, game: &mut Game,
+                     actors: &mut Vec<Object>) -> PlayerAction {
+    match (purpose, outcome) {
+        (Purpose::Quit, Outcome::Confirmed) => Exit,
+        (Purpose::UseItem, Outcome::Chosen(inventory_id)) => {
+            perform(actor::choose_use(inventory_id, keymap, game_ui, game,
+                                      actors))
+        }
+        (Purpose::DropItem, Outcome::Chosen(inventory_id)) => {
+            Perform(Command::Drop(inventory_id))
+        }
+        (Purpose::ExamineItem, Outcome::Chosen(inventory_id)) => {
+            if let Some(ref inventory) = actors[consts::PLAYER].inventory {
+                game_ui.windows.push(examine_window(&inventory[inventory_id]));
+            }
+            Nothing
+        }
+        _ => Nothing,
+    }
+}
+
+/// Head for the tile the player clicked on, if they have been there.
+pub fn handle_click(game_ui: &Ui, game: &Game, actors: &[Object])
+                    -> PlayerAction {
+    let tile = game_ui.layout.map_tile(game_ui.mouse.cx as i32,
+                                       game_ui.mouse.cy as i32);
+    match tile {
+        Some((x, y)) if actors[consts::PLAYER].alive &&
+            game.map[x as usize][y as usize].explored &&
+            (x, y) != actors[consts::PLAYER].pos() =>
+            Travel(Destination::Tile(x, y)),
+        _ => Nothing,
+    }
+}
+
+fn perform(command: Option<Command>) -> PlayerAction {
+    match command {
+        Some(command) => Perform(command),
+        None => Nothing,
+    }
+}
+
+fn step(dx: i32, dy: i32, data: &Data, game_ui: &mut Ui, game: &mut Game,
+        actors: &mut Vec<Object>) -> PlayerAction {
+    perform(actor::choose_step(dx, dy, &data.dialogues, game_ui, game,
+                               actors))
+}
@@ -3,15 +3,45 @@ extern crate rustc_serialize;
 extern crate tcod;
 
 mod ai;
+mod background;
+mod command;
 mod consts;
+mod crafting;
+mod dialogue;
+mod fire;
 mod game;
+mod horde;
+mod keymap;
 mod keys;
 mod log;
 mod map;
+mod noise;
 mod object;
+mod replay;
+mod scheduler;
+mod settings;
+mod sight;
+mod travel;
 mod ui;
 mod util;
 
+use std::env;
+
+use ui::backend;
+
 fn main() {
-    game::start_game();
+    let args: Vec<String> = env::args().skip(1).collect();
+    // Works with or without a replay
+    let kind = if args.iter().any(|arg| arg == "--terminal") {
+        backend::Kind::Terminal
+    } else {
+        backend::Kind::Tcod
+    };
+    let args: Vec<String> = args.into_iter()
+        .filter(|arg| arg != "--terminal").collect();
+    if args.is_empty() {
+        game::start_game(kind);
+    } else {
+        replay::run(&args, kind);
+    }
 }
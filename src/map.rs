@@ -1,17 +1,19 @@
 use std::cmp;
 
-use rand::{self, Rng};
+use rand::Rng;
 
 use tcod::bsp::{Bsp, TraverseOrder};
+use tcod::colors::Color;
+use tcod::random::{self, Algo};
+use tcod::line::Line;
 
 use consts;
-use object::{self, actor, Object, ObjectClass};
+use game::Data;
+use object::{self, actor, container, Object, ObjectClass};
 use object::load::ObjectRandomizer;
 use object::item::Function;
 use ai::Ai;
-
-pub const MAP_WIDTH: i32 = 80;
-pub const MAP_HEIGHT: i32 = 43;
+use util::rng;
 
 pub const FLOOR_WIDTH: i32 = 30;
 pub const FLOOR_HEIGHT: i32 = 30;
@@ -23,12 +25,55 @@ pub const MAX_ROOMS: i32 = 30;
 
 pub const MAX_ROOM_MONSTERS: i32 = 3;
 pub const MAX_ROOM_ITEMS:i32 = 4;
+// Percent chance for each room to get a piece of lootable furniture
+pub const CONTAINER_CHANCE: i32 = 60;
+pub const MIN_HORDE_SIZE: i32 = 2;
+pub const MAX_HORDE_SIZE: i32 = 5;
+pub const MAX_SURVIVORS: i32 = 2;
+
+/// Items every floor is built from, which the items file has to have.
+pub const FLOOR_ITEMS: [&'static str; 3] = ["brick wall", "concrete floor",
+                                            "stairs up"];
+pub const DOOR_TYPE: &'static str = "door";
+
+/// How something looked when the player last saw it.
+#[derive(Clone, Debug, RustcEncodable, RustcDecodable)]
+pub struct Remembered {
+    pub color: Color,
+    pub description: String,
+    pub name: String,
+    pub object_type: String,
+    // Whether it had been searched yet, for containers
+    pub searched: Option<bool>,
+    pub symbol: char,
+}
+
+impl Remembered {
+    pub fn of(object: &Object) -> Self {
+        Remembered {
+            color: object.color,
+            description: object.description.clone(),
+            name: object.display_name(),
+            object_type: object.object_type.clone(),
+            searched: object.container.as_ref().map(|c| c.searched),
+            symbol: object.symbol,
+        }
+    }
+}
 
 #[derive(Debug, RustcEncodable, RustcDecodable)]
 pub struct Tile {
     pub floor: Object,
     pub explored: bool,
+    // Turns left for fire burning here, 0 if there is none
+    pub fire: i32,
     pub items: Vec<Object>,
+    // The items here as they were when the tile was last in view
+    pub seen_items: Vec<Remembered>,
+    // A monster spotted here that the player hasn't seen leave
+    pub seen_monster: Option<Remembered>,
+    // Turns left for smoke hanging here, which blocks the view
+    pub smoke: i32,
 }
 
 impl Tile {
@@ -36,7 +81,11 @@ impl Tile {
         Tile{
             floor: floor.create_object(),
             explored: false,
-            items: vec![],}
+            fire: 0,
+            items: vec![],
+            seen_items: vec![],
+            seen_monster: None,
+            smoke: 0,}
     }
 }
 
@@ -75,6 +124,9 @@ pub fn blocks_view(x: i32, y: i32, map: &Map, actors: &[Object]) -> object::Bloc
     // If only one thing blocks fully we know nothing can see through that
     // tile, so we are done. If something only partially blocks, we
     // have to keep checking in case there is something fully blocking.
+    if map[x as usize][y as usize].smoke > 0 {
+        return object::Blocks::Full
+    }
     let mut blocks = object::Blocks::No;
     for actor in actors {
         if actor.x == x && actor.y == y {
@@ -94,6 +146,35 @@ pub fn blocks_view(x: i32, y: i32, map: &Map, actors: &[Object]) -> object::Bloc
     blocks
 }
 
+/// Trace a shot from `from` to `to`. Returns `None` if something fully
+/// blocks the way, otherwise the number of tiles of partial cover the
+/// shot has to pass through. Neither end of the line counts as cover.
+pub fn line_of_fire(from: (i32, i32), to: (i32, i32), map: &Map,
+                    actors: &[Object]) -> Option<i32> {
+    let mut cover = 0;
+    for (x, y) in Line::new(from, to) {
+        if (x, y) == to {
+            break;
+        }
+        let blocks = cmp::max(is_blocked(x, y, map, actors),
+                              blocks_view(x, y, map, actors));
+        match blocks {
+            object::Blocks::Full => return None,
+            object::Blocks::Half => cover += 1,
+            object::Blocks::No => {}
+        }
+    }
+    Some(cover)
+}
+
+/// Whether anything standing at `from` can see `to`, with nothing that
+/// fully blocks the view in between.
+pub fn line_of_sight(from: (i32, i32), to: (i32, i32), map: &Map,
+                     actors: &[Object]) -> bool {
+    Line::new(from, to).take_while(|&tile| tile != to).all(|(x, y)| {
+        blocks_view(x, y, map, actors) != object::Blocks::Full
+    })
+}
 
 pub type Map = Vec<Vec<Tile>>;
 
@@ -138,11 +219,11 @@ fn place_objects(floor: usize,
     if floor == 1 {
         let mut stairs = (0, 0);
         for room in rooms {
-            let ref mut door_randomizer = items.create_randomizer("door").unwrap();
+            let ref mut door_randomizer = items.create_randomizer(DOOR_TYPE).unwrap();
             if room.x1 == 1 && room.y1 == 1 {
                 make_door(0, room.y2 / 2, door_randomizer, map);
             } else if room.y2 == FLOOR_HEIGHT - 1 || room.x2 == FLOOR_WIDTH - 1 {
-                if stairs == (0, 0) || rand::random() {
+                if stairs == (0, 0) || rng::game_rng().gen() {
                     let stairs_x = room.x1 + ((room.x2 - room.x1)/2);
                     let stairs_y = room.y1 + ((room.y2 - room.y1)/2);
                     stairs = (stairs_x, stairs_y);
@@ -150,13 +231,14 @@ fn place_objects(floor: usize,
             }
         }
         let (stairs_x, stairs_y) = stairs;
-        let mut stairs_up = items.get_class("stairs up").create_object();
+        let mut stairs_up = items.get_class("stairs up")
+            .expect("checked in load_data").create_object();
         stairs_up.set_pos(stairs_x, stairs_y);
         map[stairs_x as usize][stairs_y as usize].items.push(stairs_up);
     }
 
-    for _ in 0..rand::thread_rng().gen_range(1,3) {
-        let room = rooms[rand::thread_rng().gen_range(0, rooms.len())];
+    for _ in 0..rng::game_rng().gen_range(1,3) {
+        let room = rooms[rng::game_rng().gen_range(0, rooms.len())];
         let brick_x = room.x1 + 1;
         let brick_y = room.y1 + 2;
         if let Some(ref mut brick_random) = items.create_randomizer(
@@ -169,10 +251,63 @@ fn place_objects(floor: usize,
 
     }
 
+    for _ in 0..rng::game_rng().gen_range(0, 2) {
+        place_random_item("melee weapon", rooms, map, items);
+    }
+    for _ in 0..rng::game_rng().gen_range(0, 3) {
+        place_random_item("clothing", rooms, map, items);
+    }
+    for _ in 0..rng::game_rng().gen_range(0, 2) {
+        place_random_item("ranged weapon", rooms, map, items);
+    }
+    for _ in 0..rng::game_rng().gen_range(1, 5) {
+        place_random_item("ammo", rooms, map, items);
+    }
+    for _ in 0..rng::game_rng().gen_range(1, 4) {
+        place_random_item("material", rooms, map, items);
+    }
+
+    for room in rooms {
+        if rng::game_rng().gen_range(0, 100) < CONTAINER_CHANCE {
+            place_container(room, map, items);
+        }
+    }
+
+}
+
+fn place_random_item(type_name: &str, rooms: &Vec<Rect>, map: &mut Map,
+                     items: &object::load::ObjectTypes) {
+    let room = rooms[rng::game_rng().gen_range(0, rooms.len())];
+    let x = rng::game_rng().gen_range(room.x1 + 1, room.x2);
+    let y = rng::game_rng().gen_range(room.y1 + 1, room.y2);
+    if let Some(ref mut randomizer) = items.create_randomizer(type_name) {
+        let mut item = randomizer.get_class().create_object();
+        if item.stackable {
+            item.count = rng::game_rng().gen_range(1, 6);
+        }
+        item.set_pos(x, y);
+        map[x as usize][y as usize].items.push(item);
+    }
+}
+
+fn place_container(room: &Rect, map: &mut Map,
+                   items: &object::load::ObjectTypes) {
+    let x = rng::game_rng().gen_range(room.x1 + 1, room.x2);
+    let y = rng::game_rng().gen_range(room.y1 + 1, room.y2);
+    if !map[x as usize][y as usize].items.is_empty() {
+        return;
+    }
+    if let Some(ref mut randomizer) = items.create_randomizer("container") {
+        let mut furniture = randomizer.get_class().create_object();
+        furniture.set_pos(x, y);
+        container::fill(&mut furniture, items);
+        map[x as usize][y as usize].items.push(furniture);
+    }
 }
 
 fn place_actors(floor: usize, rooms: &Vec<Rect>, map: &mut Map,
                 actor_types: &object::load::ObjectTypes,
+                item_types: &object::load::ObjectTypes,
                 actors: &mut Vec<Object>) {
     for room in rooms {
         if room.x1 == 1 && room.y1 == 1 {
@@ -180,17 +315,55 @@ fn place_actors(floor: usize, rooms: &Vec<Rect>, map: &mut Map,
         }
     }
 
-    for _ in 0..rand::thread_rng().gen_range(1,2) {
-        let room = rooms[rand::thread_rng().gen_range(0, rooms.len())];
-        let x = rand::thread_rng().gen_range(room.x1+1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1+1, room.y2);
-        if let Some(ref mut zombie_random) = actor_types.create_randomizer(
-            "zombie") {
-            let zombie_class = &mut zombie_random.get_class();
-            let mut zombie = zombie_class.create_object();
-            zombie.set_pos(x, y);
-            actors.push(zombie);
+    // Loners wander on their own, hordes start out packed into one room
+    // away from the player
+    for _ in 0..rng::game_rng().gen_range(1, 4) {
+        let room = rooms[rng::game_rng().gen_range(0, rooms.len())];
+        spawn_actor("zombie", room, None, map, actor_types, item_types,
+                    actors);
+    }
+    for _ in 0..rng::game_rng().gen_range(0, MAX_SURVIVORS + 1) {
+        let room = rooms[rng::game_rng().gen_range(0, rooms.len())];
+        spawn_actor("survivor", room, None, map, actor_types, item_types,
+                    actors);
+    }
+    let far_rooms: Vec<&Rect> = rooms.iter()
+        .filter(|room| !(room.x1 == 1 && room.y1 == 1)).collect();
+    if far_rooms.is_empty() {
+        return;
+    }
+    for horde_id in 0..rng::game_rng().gen_range(1, 3) {
+        let room = *far_rooms[rng::game_rng().gen_range(0, far_rooms.len())];
+        let size = rng::game_rng().gen_range(MIN_HORDE_SIZE,
+                                                 MAX_HORDE_SIZE + 1);
+        for _ in 0..size {
+            spawn_actor("zombie", room, Some(horde_id), map, actor_types,
+                        item_types, actors);
+        }
+    }
+}
+
+/// Put a random actor of `actor_type` somewhere free in `room`, if there
+/// is any space, with its pockets filled from its loot table.
+fn spawn_actor(actor_type: &str, room: Rect, horde: Option<usize>, map: &Map,
+                actor_types: &object::load::ObjectTypes,
+                item_types: &object::load::ObjectTypes,
+                actors: &mut Vec<Object>) {
+    for _ in 0..10 {
+        let x = rng::game_rng().gen_range(room.x1 + 1, room.x2);
+        let y = rng::game_rng().gen_range(room.y1 + 1, room.y2);
+        if is_blocked(x, y, map, actors) != object::Blocks::No {
+            continue;
+        }
+        if let Some(ref mut randomizer) = actor_types.create_randomizer(
+            actor_type) {
+            let mut actor = randomizer.get_class().create_object();
+            actor.set_pos(x, y);
+            actor.horde = horde;
+            container::fill(&mut actor, item_types);
+            actors.push(actor);
         }
+        return;
     }
 }
 
@@ -244,14 +417,14 @@ fn traverse_node(node: &mut Bsp, rooms: &mut Vec<Rect>,
 }
 
 
-pub fn make_map(mut actors: &mut Vec<Object>) -> Map {
+pub fn make_map(mut actors: &mut Vec<Object>, data: &Data) -> Map {
     let mut map = vec![];
-    let actor_types = object::load::load_objects(
-        "data/objects/actors.json").unwrap();
-    let item_types = object::load::load_objects(
-        "data/objects/items.json").unwrap();
-    let wall_class = item_types.get_class("brick wall");
-    let concrete_floor = item_types.get_class("concrete floor");
+    let actor_types = &data.actor_types;
+    let item_types = &data.item_types;
+    let wall_class = item_types.get_class("brick wall")
+        .expect("checked in load_data");
+    let concrete_floor = item_types.get_class("concrete floor")
+        .expect("checked in load_data");
     for x in 0..FLOOR_WIDTH {
         map.push(vec![]);
         for y in 0..FLOOR_HEIGHT {
@@ -264,11 +437,14 @@ pub fn make_map(mut actors: &mut Vec<Object>) -> Map {
     }
     let mut rooms = vec![];
     let mut bsp = Bsp::new_with_size(0, 0, FLOOR_WIDTH, FLOOR_HEIGHT);
-    bsp.split_recursive(None, 3, ROOM_MIN_X, ROOM_MIN_Y, 1.25, 1.25);
+    // Split with a generator seeded from ours so the layout can be replayed
+    let splitter = random::Rng::new_with_seed(Algo::CMWC,
+                                              rng::game_rng().gen());
+    bsp.split_recursive(Some(splitter), 3, ROOM_MIN_X, ROOM_MIN_Y, 1.25, 1.25);
     bsp.traverse(TraverseOrder::InvertedLevelOrder, |node| {
-        traverse_node(node, &mut rooms, &item_types, &concrete_floor, &mut map)
+        traverse_node(node, &mut rooms, item_types, &concrete_floor, &mut map)
     });
-    place_objects(1, &rooms, &mut map, &item_types);
-    place_actors(1, &rooms, &mut map, &actor_types, &mut actors);
+    place_objects(1, &rooms, &mut map, item_types);
+    place_actors(1, &rooms, &mut map, actor_types, item_types, &mut actors);
     map
 }
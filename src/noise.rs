@@ -0,0 +1,32 @@
+use object::Object;
+
+/// A sound made somewhere on the map during the current turn. Monsters
+/// within `volume` tiles of it can hear it, walls or not.
+#[derive(Clone, Copy, Debug, RustcEncodable, RustcDecodable)]
+pub struct Noise {
+    pub x: i32,
+    pub y: i32,
+    pub volume: i32,
+}
+
+pub type Noises = Vec<Noise>;
+
+pub fn make_noise(noises: &mut Noises, x: i32, y: i32, volume: i32) {
+    if volume > 0 {
+        noises.push(Noise { x: x, y: y, volume: volume });
+    }
+}
+
+/// The noise that stands out the most to `listener`, if it can hear any.
+pub fn loudest_heard(listener: &Object, noises: &Noises) -> Option<Noise> {
+    let mut loudest = None;
+    let mut loudest_margin = 0.0;
+    for noise in noises {
+        let margin = noise.volume as f32 - listener.distance(noise.x, noise.y);
+        if margin >= 0.0 && (loudest.is_none() || margin > loudest_margin) {
+            loudest = Some(*noise);
+            loudest_margin = margin;
+        }
+    }
+    loudest
+}
@@ -1,20 +1,39 @@
+use std::cmp;
+
+use rand::Rng;
+
 use tcod::colors;
 
+use ai::Ai;
 use consts;
-use game::Game;
+use command::Command;
+use crafting::{self, Recipe};
+use dialogue::{self, Dialogue};
+use fire;
+use game::{Data, Game};
+use keymap::Keymap;
 use log;
 use log::MessageLog;
 use object::{self, Object};
-use object::item::{self, Function};
+use object::character::{self, Skill};
+use object::container::{self, Container, Transfer};
+use object::interact::{self, Interaction};
+use object::status;
+use object::item::{self, Function, Ranged, Slot};
 use map::{self, Map};
-use ui::Ui;
+use noise;
+use scheduler::Action;
+use sight::Sight;
+use ui::{self, Ui};
 use util;
+use util::rng;
 
 
 #[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
 pub enum DeathCallback {
     Player,
     Monster,
+    Survivor,
 }
 
 impl DeathCallback {
@@ -22,6 +41,7 @@ impl DeathCallback {
         let callback: fn(&mut Object, &mut log::Messages) = match self {
             DeathCallback::Player => player_death,
             DeathCallback::Monster => monster_death,
+            DeathCallback::Survivor => survivor_death,
         };
         callback(object, messages);
     }
@@ -34,45 +54,338 @@ pub struct Fighter {
     pub defense: i32,
     pub power: i32,
     pub on_death: DeathCallback,
+    // Effect passed on by a damaging attack, like an infected bite
+    pub inflicts: Option<status::Effect>,
+}
+
+/// How carefully an actor is moving. Sneaking is slow and quiet and
+/// keeps you out of sight, sprinting is fast and loud and tiring.
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum MoveMode {
+    Sneak,
+    Walk,
+    Sprint,
+}
+
+impl MoveMode {
+    /// Volume of the noise made by each step.
+    pub fn noise(self) -> i32 {
+        match self {
+            MoveMode::Sneak => 0,
+            MoveMode::Walk => 3,
+            MoveMode::Sprint => 8,
+        }
+    }
+
+    /// How far away monsters can spot the mover, relative to walking.
+    pub fn visibility(self) -> f32 {
+        match self {
+            MoveMode::Sneak => 0.5,
+            MoveMode::Walk => 1.0,
+            MoveMode::Sprint => 1.5,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MoveMode::Sneak => "Sneaking",
+            MoveMode::Walk => "Walking",
+            MoveMode::Sprint => "Sprinting",
+        }
+    }
 }
 
-pub fn move_by(id: usize, dx: i32, dy: i32, map: &Map, actors: &mut[Object]) {
+/// How a survivor feels about the player. Friendly and neutral survivors
+/// talk and trade, hostile ones attack on sight.
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Attitude {
+    Friendly,
+    Neutral,
+    Hostile,
+}
+
+impl Attitude {
+    /// Percentage of an item's value the survivor asks for it.
+    pub fn markup(self) -> i32 {
+        match self {
+            Attitude::Friendly => 100,
+            Attitude::Neutral | Attitude::Hostile => 150,
+        }
+    }
+}
+
+/// One swap agreed with a survivor: their item at `wanted` for `offered[i]`
+/// of each stack in the player's inventory.
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Trade {
+    pub offered: Vec<i32>,
+    pub wanted: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Stamina {
+    pub stamina: i32,
+    pub max_stamina: i32,
+}
+
+const DOOR_NOISE: i32 = 2;
+// Percent chance for each blow a zombie lands on a closed door to break
+// it open
+const DOOR_BASH_CHANCE: i32 = 25;
+const PUSH_NOISE: i32 = 5;
+const SPRINT_STAMINA_COST: i32 = 5;
+const STAMINA_RECOVERY: i32 = 1;
+const EXHAUSTION_TURNS: i32 = 10;
+
+pub fn move_by(id: usize, dx: i32, dy: i32, game: &mut Game,
+               actors: &mut[Object]) {
     let (x, y) = actors[id].pos();
-    if map::is_blocked(x + dx, y + dy, map, actors) == object::Blocks::No {
-        actors[id].set_pos(x + dx, y + dy);
+    let (door_x, door_y) = (x + dx, y + dy);
+    // Walking into a closed door spends the step on it. The living open
+    // it, the dead batter away until it gives.
+    if let Some(door_id) = interact::door_at(door_x, door_y, &game.map) {
+        let opens = id == consts::PLAYER || actors[id].attitude.is_some() ||
+            rng::game_rng().gen_range(0, 100) < DOOR_BASH_CHANCE;
+        if opens {
+            interact::open_door(&mut game.map[door_x as usize]
+                                [door_y as usize].items[door_id]);
+        }
+        noise::make_noise(&mut game.noises, door_x, door_y, DOOR_NOISE);
+        return;
     }
+    if map::is_blocked(door_x, door_y, &game.map, actors) ==
+        object::Blocks::No {
+            actors[id].set_pos(door_x, door_y);
+        }
 }
 
-pub fn move_towards(id: usize, target_x: i32, target_y: i32, map: &Map,
+pub fn move_towards(id: usize, target_x: i32, target_y: i32, game: &mut Game,
                 actors: &mut [Object]) {
     let dx = target_x - actors[id].x;
     let dy = target_y - actors[id].y;
     let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
 
-    let dx = (dx as f32 / distance).round() as i32;
-    let dy = (dy as f32 / distance).round() as i32;
+    let mut dx = (dx as f32 / distance).round() as i32;
+    let mut dy = (dy as f32 / distance).round() as i32;
+
+    // Step around fire rather than through it if there is a way
+    let (x, y) = actors[id].pos();
+    if fire::is_burning(x + dx, y + dy, &game.map) {
+        if dx != 0 && !fire::is_burning(x + dx, y, &game.map) {
+            dy = 0;
+        } else if dy != 0 && !fire::is_burning(x, y + dy, &game.map) {
+            dx = 0;
+        } else {
+            return;
+        }
+    }
 
-    move_by(id, dx, dy, map, actors);
+    move_by(id, dx, dy, game, actors);
 }
 
+/// Work out what walking the player (dx, dy) means: a plain step, an
+/// attack, or whatever they choose to do to the thing in the way, asking
+/// which if there is a choice. Returns None if the player backed out.
+pub fn choose_step(dx: i32, dy: i32, dialogues: &[Dialogue],
+                   game_ui: &mut Ui, game: &mut Game, actors: &mut [Object])
+                   -> Option<Command> {
+    let x = actors[consts::PLAYER].x + dx;
+    let y = actors[consts::PLAYER].y + dy;
+
+    let target_id = actors.iter().position(|object| {
+        object.fighter.is_some() && object.pos() == (x, y)
+    });
+    if let Some(target_id) = target_id {
+        if is_hostile(&actors[target_id]) {
+            return Some(Command::Move(dx, dy));
+        }
+        let interaction = {
+            let target = &actors[target_id];
+            choose_interaction(&target.name, &target.interactions, game_ui)
+        };
+        return match interaction {
+            Some(Interaction::Talk) =>
+                choose_talk(target_id, dialogues, game_ui, game, actors),
+            Some(interaction) => Some(Command::Interact(dx, dy, interaction)),
+            None => None,
+        };
+    }
+
+    // Only things that stand in the way get bumped, the rest are walked
+    // over
+    let bumped = game.map[x as usize][y as usize].items.iter()
+        .position(|item| {
+            item.blocks != object::Blocks::No && !item.interactions.is_empty()
+        });
+    if let Some(item_id) = bumped {
+        let interaction = {
+            let item = &game.map[x as usize][y as usize].items[item_id];
+            choose_interaction(&item.name, &item.interactions, game_ui)
+        };
+        return match interaction {
+            Some(Interaction::Search) => {
+                let location = container::Location::Tile(x, y, item_id);
+                let transfers = choose_transfers(location, game_ui, game,
+                                                 actors);
+                Some(Command::Search(location, transfers))
+            }
+            Some(interaction) => Some(Command::Interact(dx, dy, interaction)),
+            None => None,
+        };
+    }
+    Some(Command::Move(dx, dy))
+}
+
+/// Pick one of `interactions` with `name`, asking only if there is more
+/// than one to choose from.
+fn choose_interaction(name: &str, interactions: &[Interaction],
+                      game_ui: &mut Ui) -> Option<Interaction> {
+    match interactions.len() {
+        0 => None,
+        1 => Some(interactions[0]),
+        _ => ui::interaction_menu(name, interactions, game_ui)
+            .map(|choice| interactions[choice]),
+    }
+}
+
+/// Whether the player fights `object` on sight rather than asking what to
+/// do with it. Only survivors have an attitude to be peaceful with.
+pub fn is_hostile(object: &Object) -> bool {
+    object.attitude.map_or(true, |a| a == Attitude::Hostile)
+}
+
+/// Walk the player one step, attacking anything hostile in the way.
+/// Returns the action taken, or None if a peaceful survivor stood there.
 pub fn player_move_or_attack(dx: i32, dy: i32, game: &mut Game,
-                         actors: &mut [Object]) {
+                             actors: &mut [Object]) -> Option<Action> {
     let x = actors[consts::PLAYER].x + dx;
     let y = actors[consts::PLAYER].y + dy;
 
     let target_id = actors.iter().position(|object| {
         object.fighter.is_some() && object.pos() == (x, y)
     });
+    if let Some(target_id) = target_id {
+        if !is_hostile(&actors[target_id]) {
+            return None;
+        }
+        player_attack(target_id, game, actors);
+        return Some(Action::Attack);
+    }
 
-    match target_id {
-        Some(target_id) => {
-            let (player, target) =
-                util::mut_two(consts::PLAYER, target_id, actors);
-            player.attack(target, &mut game.log);
+    move_by(consts::PLAYER, dx, dy, game, actors);
+    let player = &mut actors[consts::PLAYER];
+    // Practised feet make less noise at any pace
+    let volume = cmp::max(0, player.move_mode.noise() -
+                          character::level(player, Skill::Stealth) / 3);
+    noise::make_noise(&mut game.noises, player.x, player.y, volume);
+    if player.move_mode == MoveMode::Sneak {
+        character::practice(player, Skill::Stealth, 1, &mut game.log);
+    }
+    Some(Action::Move)
+}
+
+/// Do `interaction` to whatever is in the way (dx, dy) from the player.
+/// Searching and talking have commands of their own.
+pub fn interact(dx: i32, dy: i32, interaction: Interaction, game: &mut Game,
+                actors: &mut [Object]) -> Option<Action> {
+    let x = actors[consts::PLAYER].x + dx;
+    let y = actors[consts::PLAYER].y + dy;
+    match interaction {
+        Interaction::Attack => {
+            let target_id = actors.iter().position(|object| {
+                object.fighter.is_some() && object.pos() == (x, y)
+            });
+            target_id.map(|target_id| {
+                player_attack(target_id, game, actors);
+                Action::Attack
+            })
         }
-        None => {
-            move_by(consts::PLAYER, dx, dy, &mut game.map, actors);
+        Interaction::Open | Interaction::Push => {
+            let item_id = game.map[x as usize][y as usize].items.iter()
+                .position(|item| item.interactions.contains(&interaction));
+            item_id.and_then(|item_id| {
+                bump_item(x, y, item_id, dx, dy, interaction, game, actors)
+            })
         }
+        Interaction::Search | Interaction::Talk => None,
+    }
+}
+
+fn player_attack(target_id: usize, game: &mut Game, actors: &mut [Object]) {
+    let (player, target) = util::mut_two(consts::PLAYER, target_id, actors);
+    let chance = character::melee_hit_chance(player);
+    if rng::game_rng().gen_range(0, 100) < chance {
+        player.attack(target, &mut game.log);
+    } else {
+        game.log.info(format!("You swing at {} but miss.", target.name));
+    }
+    provoke(target, &mut game.log);
+    character::practice(player, Skill::Melee, 1, &mut game.log);
+}
+
+/// Do `interaction` to the item at `item_id` on the tile (x, y), which
+/// the player bumped into going (dx, dy).
+fn bump_item(x: i32, y: i32, item_id: usize, dx: i32, dy: i32,
+             interaction: Interaction, game: &mut Game,
+             actors: &mut [Object]) -> Option<Action> {
+    let name = game.map[x as usize][y as usize].items[item_id].name.clone();
+    match interaction {
+        Interaction::Open => {
+            interact::open_door(
+                &mut game.map[x as usize][y as usize].items[item_id]);
+            game.log.info(format!("You open the {}.", name));
+            noise::make_noise(&mut game.noises, x, y, DOOR_NOISE);
+            Some(Action::OpenDoor)
+        }
+        Interaction::Push => {
+            if interact::push(x, y, item_id, dx, dy, &mut game.map, actors) {
+                game.log.info(format!("You shove the {} along.", name));
+                noise::make_noise(&mut game.noises, x, y, PUSH_NOISE);
+                Some(Action::Push)
+            } else {
+                game.log.info(format!("The {} won't budge.", name));
+                None
+            }
+        }
+        Interaction::Attack | Interaction::Search | Interaction::Talk => None,
+    }
+}
+
+/// Switch the player between `mode` and walking.
+pub fn toggle_move_mode(mode: MoveMode, player: &mut Object,
+                        log: &mut log::Messages) {
+    let exhausted = status::has(player, status::Kind::Exhausted);
+    player.move_mode = if player.move_mode == mode {
+        MoveMode::Walk
+    } else if mode == MoveMode::Sprint && exhausted {
+        log.alert("You are too out of breath to sprint.");
+        return;
+    } else {
+        mode
+    };
+    log.info(format!("You are now {}.", player.move_mode.name().to_lowercase()));
+}
+
+/// Tire the player out for sprinting, or let them catch their breath
+/// doing anything else.
+pub fn spend_stamina(action: Action, player: &mut Object,
+                     log: &mut log::Messages) {
+    let sprinting = action == Action::Move && player.move_mode == MoveMode::Sprint;
+    if let Some(ref mut stamina) = player.stamina {
+        if sprinting {
+            stamina.stamina = cmp::max(0, stamina.stamina - SPRINT_STAMINA_COST);
+        } else {
+            stamina.stamina = cmp::min(stamina.max_stamina,
+                                       stamina.stamina + STAMINA_RECOVERY);
+        }
+    }
+    if sprinting && player.stamina.map_or(false, |s| s.stamina == 0) {
+        player.move_mode = MoveMode::Walk;
+        log.alert("You are out of breath and slow to a walk.");
+        status::apply(player, status::Effect { kind: status::Kind::Exhausted,
+                                               turns: EXHAUSTION_TURNS,
+                                               potency: 1 },
+                      log);
     }
 }
 
@@ -82,65 +395,740 @@ fn player_death(player: &mut Object, log: &mut log::Messages) {
     player.color = colors::DARK_RED;
 }
 
+/// A survivor who dies with the infection in them gets right back up.
+fn survivor_death(survivor: &mut Object, log: &mut log::Messages) {
+    if status::has(survivor, status::Kind::Infected) {
+        turn_into_zombie(survivor, log);
+    } else {
+        monster_death(survivor, log);
+    }
+}
+
+/// The infection has run its course and `object` is one of them now.
+pub fn turn_into_zombie(object: &mut Object, log: &mut log::Messages) {
+    log.alert(format!("{} turns into a zombie!", object.name));
+    object.name = format!("zombie {}", object.name);
+    object.color = colors::DARK_GREEN;
+    object.background = None;
+    object.alive = true;
+    object.blocks = object::Blocks::Full;
+    object.ai = Some(Ai::Basic);
+    object.attitude = None;
+    object.dialogue = None;
+    object.status.retain(|effect| effect.kind != status::Kind::Infected);
+    if let Some(ref mut fighter) = object.fighter {
+        fighter.hp = fighter.max_hp;
+        fighter.on_death = DeathCallback::Monster;
+        fighter.inflicts = Some(status::Effect {
+            kind: status::Kind::Infected,
+            turns: 100,
+            potency: 1,
+        });
+    }
+}
+
+/// Attacking a survivor makes an enemy of them.
+fn provoke(target: &mut Object, log: &mut log::Messages) {
+    if target.alive && target.attitude.map_or(false, |a| a != Attitude::Hostile) {
+        log.alert(format!("{} turns on you!", target.name));
+        target.attitude = Some(Attitude::Hostile);
+    }
+}
+
 fn monster_death(monster: &mut Object, log: &mut log::Messages) {
     log.status_change(format!("{} is dead!", monster.name));
     monster.symbol = '%';
     monster.color = colors::DARK_RED;
+    monster.background = None;
     monster.blocks = object::Blocks::No;
     monster.blocks_view = object::Blocks::No;
     monster.fighter = None;
     monster.ai = None;
+    monster.attitude = None;
+    monster.dialogue = None;
+    // Whatever the monster carried can now be searched for
+    if monster.container.is_none() {
+        monster.container = Some(Container {
+            searched: false,
+            search_turns: 2,
+            noise: 1,
+            loot_rolls: 0,
+            loot_table: vec![],
+        });
+    }
     monster.name = format!("remains of {}", monster.name);
 }
 
+pub fn equip(inventory_id: usize, inventory: &mut [Object],
+             log: &mut log::Messages) {
+    let slot = match inventory[inventory_id].equipment {
+        Some(ref equipment) => equipment.slot,
+        None => return,
+    };
+    // Only one thing fits in each slot, so swap out whatever is there
+    let occupied = inventory.iter().position(|item| {
+        item.equipment.as_ref().map_or(false, |e| e.equipped && e.slot == slot)
+    });
+    if let Some(occupied_id) = occupied {
+        unequip(occupied_id, inventory, log);
+    }
+    let item = &mut inventory[inventory_id];
+    if let Some(ref mut equipment) = item.equipment {
+        equipment.equipped = true;
+        log.success(format!("Equipped {} on {}.", item.name, slot));
+    }
+}
+
+pub fn unequip(inventory_id: usize, inventory: &mut [Object],
+               log: &mut log::Messages) {
+    let item = &mut inventory[inventory_id];
+    if let Some(ref mut equipment) = item.equipment {
+        if equipment.equipped {
+            equipment.equipped = false;
+            log.info(format!("Took {} off of {}.", item.name, equipment.slot));
+        }
+    }
+}
+
+pub fn toggle_equipment(inventory_id: usize, inventory: &mut [Object],
+                        log: &mut log::Messages) {
+    let equipped = match inventory[inventory_id].equipment {
+        Some(ref equipment) => equipment.equipped,
+        None => return,
+    };
+    if equipped {
+        unequip(inventory_id, inventory, log);
+    } else {
+        equip(inventory_id, inventory, log);
+    }
+}
+
 pub fn drop_item(x: i32, y: i32, inventory_id: usize, game: &mut Game,
              inventory: &mut Vec<Object>) {
+    unequip(inventory_id, inventory, &mut game.log);
     let mut item = inventory.remove(inventory_id);
     item.set_pos(x, y);
-    game.log.info(format!("You dropped a {}.", item.name));
-    game.map[x as usize][y as usize].items.push(item);
+    game.log.info(format!("You dropped {}.", item.display_name()));
+    let ref mut tile_items = game.map[x as usize][y as usize].items;
+    match tile_items.iter().position(|other| other.stacks_with(&item)) {
+        Some(stack_id) => tile_items[stack_id].count += item.count,
+        None => tile_items.push(item),
+    }
+}
+
+/// Put `item` in `inventory`, merging it into a matching stack if
+/// there is one.
+pub fn add_to_inventory(mut item: Object, inventory: &mut Vec<Object>) {
+    item.set_pos(-1, -1);
+    match inventory.iter().position(|other| other.stacks_with(&item)) {
+        Some(stack_id) => inventory[stack_id].count += item.count,
+        None => inventory.push(item),
+    }
+}
+
+/// Take a single item off the stack at `inventory_id`, removing the
+/// inventory entry once the stack is empty.
+pub fn take_one(inventory_id: usize, inventory: &mut Vec<Object>) -> Object {
+    if inventory[inventory_id].count > 1 {
+        inventory[inventory_id].count -= 1;
+        let mut item = inventory[inventory_id].clone();
+        item.count = 1;
+        item
+    } else {
+        inventory.remove(inventory_id)
+    }
+}
+
+pub fn carried_weight(inventory: &[Object]) -> f32 {
+    inventory.iter().map(|item| item.total_weight()).sum()
 }
 
-pub fn pick_up_items(x: i32, y: i32, inventory: &mut Vec<Object>, game: &mut Game) {
+/// Weight `object` can carry before slowing down, in kilograms.
+pub fn carry_capacity(object: &Object) -> f32 {
+    consts::PLAYER_CARRY_CAPACITY +
+        2.5 * character::modifier(object, |a| a.strength) as f32
+}
+
+/// Whether `object` is carrying more than it can comfortably move with.
+pub fn is_overburdened(object: &Object) -> bool {
+    object.inventory.as_ref().map_or(false, |inventory| {
+        carried_weight(inventory) > carry_capacity(object)
+    })
+}
+
+pub fn pick_up_items(x: i32, y: i32, inventory: &mut Vec<Object>,
+                     capacity: f32, game: &mut Game) {
     let mut names = vec![];
     let ref mut items = game.map[x as usize][y as usize].items;
     for item_ix in (0..items.len()).rev() {
         if items[item_ix].can_pick_up {
-            let mut item = items.swap_remove(item_ix);
-            item.set_pos(-1, -1);
-            names.push(item.name.clone());
-            inventory.push(item);
+            let item = items.swap_remove(item_ix);
+            names.push(item.display_name());
+            add_to_inventory(item, inventory);
         }
     }
     if names.len() > 0{
         game.log.info(format!("You picked up {}", names.join(", ")));
     }
+    warn_if_overburdened(inventory, capacity, &mut game.log);
 
 }
 
-pub fn use_item(game_ui: &mut Ui, game: &mut Game,
-                inventory_id: usize, inventory: &mut Vec<Object>) {
-    println!("{:?}", inventory.len());
-    if let Some(function) = inventory[inventory_id].function {
+/// Pick up the single object at `item_id` in the tile's item list.
+pub fn pick_up_item(x: i32, y: i32, item_id: usize, inventory: &mut Vec<Object>,
+                    capacity: f32, game: &mut Game) {
+    let item = game.map[x as usize][y as usize].items.remove(item_id);
+    game.log.info(format!("You picked up {}", item.display_name()));
+    add_to_inventory(item, inventory);
+    warn_if_overburdened(inventory, capacity, &mut game.log);
+}
+
+fn warn_if_overburdened(inventory: &[Object], capacity: f32,
+                        log: &mut log::Messages) {
+    if carried_weight(inventory) > capacity {
+        log.alert("You are carrying too much and slow down.");
+    }
+}
+
+/// Ask for whatever the inventory item at `inventory_id` needs before it
+/// can be used: a tile to throw it at, or an enemy to aim it at.
+pub fn choose_use(inventory_id: usize, keymap: &Keymap, game_ui: &mut Ui,
+                  game: &mut Game, actors: &[Object]) -> Option<Command> {
+    let function = actors[consts::PLAYER].inventory.as_ref()
+        .unwrap()[inventory_id].function;
+    match function {
+        Some(Function::Fireball) => {
+            game.log.info("Pick a tile to throw the molotov at with the \
+                           movement keys and Enter, or a left-click. Escape \
+                           or a right-click cancels.");
+            match item::target_tile(keymap, game_ui, game, actors, None) {
+                Some(target) => Some(Command::Throw(inventory_id, target)),
+                None => {
+                    game.log.info("Cancelled");
+                    None
+                }
+            }
+        }
+        Some(Function::Stun) => {
+            game.log.info("Pick an enemy to confuse with the movement keys \
+                           and Enter, or a left-click. Escape or a \
+                           right-click cancels.");
+            match item::target_monster(keymap, game_ui, game, actors,
+                                       Some(5.0)) {
+                Some(monster_id) => Some(Command::UseItem(
+                    inventory_id, Some(actors[monster_id].pos()))),
+                None => {
+                    game.log.alert("No enemy is within range.");
+                    game.log.info("Cancelled");
+                    None
+                }
+            }
+        }
+        _ => Some(Command::UseItem(inventory_id, None)),
+    }
+}
+
+/// Use or equip an item from the player's inventory, aimed at `target` if
+/// it needs one. Returns the action it took if it was actually used.
+pub fn use_item(inventory_id: usize, target: Option<(i32, i32)>,
+                game: &mut Game, actors: &mut [Object], sight: &Sight)
+                -> Option<Action> {
+    let (function, equippable, name) = {
+        let item = &actors[consts::PLAYER].inventory.as_ref().unwrap()[inventory_id];
+        (item.function, item.equipment.is_some(), item.name.clone())
+    };
+    if let Some(function) = function {
         let on_use:
-        fn(&mut Ui, &mut Game, &mut [Object])
+        fn(Option<(i32, i32)>, &mut Game, &mut [Object], &Sight)
            -> item::UseResult = match function {
             Function::Stun => item::cast_confuse,
             Function::Fireball => item::cast_fireball,
             Function::Heal => item::heal_player,
             Function::Lightning => item::cast_lightning,
         };
-        match on_use(game_ui, game, inventory) {
+        match on_use(target, game, actors, sight) {
             item::UseResult::UsedUp => {
-                inventory.remove(inventory_id);
+                if let Some(ref mut inventory) = actors[consts::PLAYER].inventory {
+                    take_one(inventory_id, inventory);
+                }
+                match function {
+                    Function::Fireball => Some(Action::Throw),
+                    _ => Some(Action::UseItem),
+                }
             }
             item::UseResult::Cancelled => {
                 game.log.info( "Cancelled");
+                None
             }
         }
+    } else if equippable {
+        if let Some(ref mut inventory) = actors[consts::PLAYER].inventory {
+            toggle_equipment(inventory_id, inventory, &mut game.log);
+        }
+        Some(Action::UseItem)
+    } else {
+        game.log.alert(format!("The {} cannot be used.", name));
+        None
+    }
+}
+
+/// The ranged weapon in the player's main hand, as long as they have the
+/// ammunition for it. Tells the player what is missing otherwise.
+fn ready_weapon(game: &mut Game, actors: &[Object]) -> Option<Ranged> {
+    let ranged = {
+        let player = &actors[consts::PLAYER];
+        player.get_equipped_in_slot(Slot::MainHand)
+            .and_then(|id| player.inventory.as_ref().unwrap()[id].ranged.clone())
+    };
+    let ranged = match ranged {
+        Some(ranged) => ranged,
+        None => {
+            game.log.alert("You have nothing to shoot with.");
+            return None;
+        }
+    };
+    let has_ammo = actors[consts::PLAYER].inventory.as_ref()
+        .map_or(false, |inventory| {
+            inventory.iter().any(|item| item.name == ranged.ammo)
+        });
+    if !has_ammo {
+        game.log.alert(format!("You are out of {}.", ranged.ammo));
+        return None;
+    }
+    Some(ranged)
+}
+
+/// Pick a target with the cursor or the mouse for the ranged weapon in the
+/// player's main hand.
+pub fn choose_fire(keymap: &Keymap, game_ui: &mut Ui, game: &mut Game,
+                   actors: &[Object]) -> Option<Command> {
+    let ranged = match ready_weapon(game, actors) {
+        Some(ranged) => ranged,
+        None => return None,
+    };
+    game.log.info("Pick a target to shoot at with the movement keys and \
+                   Enter, or a left-click. Escape or a right-click cancels.");
+    item::target_tile(keymap, game_ui, game, actors,
+                      Some(ranged.range as f32))
+        .map(|(x, y)| Command::Fire(x, y))
+}
+
+/// Shoot whatever ranged weapon the player has in their main hand at the
+/// tile (x, y). Returns true if a shot was fired.
+pub fn player_fire(x: i32, y: i32, game: &mut Game,
+                   actors: &mut [Object]) -> bool {
+    let ranged = match ready_weapon(game, actors) {
+        Some(ranged) => ranged,
+        None => return false,
+    };
+
+    let (player_x, player_y) = actors[consts::PLAYER].pos();
+    if let Some(ref mut inventory) = actors[consts::PLAYER].inventory {
+        let ammo_id = inventory.iter().position(|item| item.name == ranged.ammo)
+            .unwrap();
+        take_one(ammo_id, inventory);
+    }
+    noise::make_noise(&mut game.noises, player_x, player_y, ranged.noise);
+
+    let target_id = actors.iter().position(|object| {
+        object.fighter.is_some() && object.pos() == (x, y)
+    });
+    let target_id = match target_id {
+        Some(id) if id != consts::PLAYER => id,
+        _ => {
+            game.log.info("You shoot at nothing in particular.");
+            return true;
+        }
+    };
+
+    let cover = match map::line_of_fire((player_x, player_y), (x, y),
+                                        &game.map, actors) {
+        Some(cover) => cover,
+        None => {
+            game.log.alert("Something is in the way of your shot.");
+            return true;
+        }
+    };
+    let distance = actors[consts::PLAYER].distance(x, y);
+    let chance = item::hit_chance(distance, cover);
+    if rng::game_rng().gen_range(0, 100) < chance {
+        let damage = ranged.damage - actors[target_id].defense();
+        if damage > 0 {
+            game.log.info(format!("You shoot the {} for {} hit points.",
+                                  actors[target_id].name, damage));
+            actors[target_id].take_damage(damage, &mut game.log);
+        } else {
+            game.log.info(format!("You shoot the {} but it shrugs it off.",
+                                  actors[target_id].name));
+        }
+    } else if cover > 0 {
+        game.log.info("Your shot hits cover.");
     } else {
-        game.log.alert(
-            format!("The {} cannot be used.",
-                    inventory[inventory_id].name));
+        game.log.info("You miss.");
+    }
+    true
+}
+
+/// Searchable containers within reach of (x, y): corpses in the actor list
+/// and furniture on the surrounding map tiles.
+pub fn containers_near(x: i32, y: i32, map: &Map, actors: &[Object])
+                       -> Vec<container::Location> {
+    let mut found = vec![];
+    for (id, object) in actors.iter().enumerate() {
+        if id != consts::PLAYER && !object.alive && object.container.is_some() &&
+            (object.x - x).abs() <= 1 && (object.y - y).abs() <= 1 {
+                found.push(container::Location::Actor(id));
+            }
+    }
+    for tile_x in x - 1..x + 2 {
+        for tile_y in y - 1..y + 2 {
+            if tile_x < 0 || tile_y < 0 || tile_x >= map::FLOOR_WIDTH ||
+                tile_y >= map::FLOOR_HEIGHT {
+                    continue;
+                }
+            let ref items = map[tile_x as usize][tile_y as usize].items;
+            for (item_id, item) in items.iter().enumerate() {
+                if item.container.is_some() {
+                    found.push(container::Location::Tile(tile_x, tile_y,
+                                                         item_id));
+                }
+            }
+        }
+    }
+    found
+}
+
+pub fn container_at<'a>(location: container::Location, map: &'a mut Map,
+                        actors: &'a mut [Object]) -> &'a mut Object {
+    match location {
+        container::Location::Actor(id) => &mut actors[id],
+        container::Location::Tile(x, y, item_id) =>
+            &mut map[x as usize][y as usize].items[item_id],
+    }
+}
+
+/// Pick a container next to the player to search, and what to move in
+/// and out of it.
+pub fn choose_search(game_ui: &mut Ui, game: &mut Game,
+                     actors: &mut [Object]) -> Option<Command> {
+    let (x, y) = actors[consts::PLAYER].pos();
+    let locations = containers_near(x, y, &game.map, actors);
+    let location = match locations.len() {
+        0 => {
+            game.log.info("There is nothing here to search.");
+            return None;
+        }
+        1 => locations[0],
+        _ => {
+            let names: Vec<_> = locations.iter().map(|&location| {
+                container_at(location, &mut game.map, actors).name.clone()
+            }).collect();
+            match ui::container_menu(&names, game_ui) {
+                Some(choice) => locations[choice],
+                None => return None,
+            }
+        }
+    };
+    let transfers = choose_transfers(location, game_ui, game, actors);
+    Some(Command::Search(location, transfers))
+}
+
+/// Let the player sort through the container at `location`. The moves are
+/// tried out on copies, so nothing changes until the search is carried
+/// out.
+fn choose_transfers(location: container::Location, game_ui: &mut Ui,
+                    game: &mut Game, actors: &mut [Object]) -> Vec<Transfer> {
+    let (name, mut contents) = {
+        let object = container_at(location, &mut game.map, actors);
+        (object.name.clone(), object.inventory.clone().unwrap_or_else(Vec::new))
+    };
+    let mut inventory = actors[consts::PLAYER].inventory.clone()
+        .unwrap_or_else(Vec::new);
+    let mut scratch = vec![];
+    let mut transfers = vec![];
+    loop {
+        ui::render_all(game_ui, game, actors, false);
+        match ui::transfer_menu(&name, &contents, &inventory, game_ui) {
+            Some(transfer) => {
+                move_item(transfer, &name, &mut contents, &mut inventory,
+                          &mut scratch);
+                transfers.push(transfer);
+            }
+            None => return transfers,
+        }
+    }
+}
+
+/// Search the container at `location` and make the `transfers` between it
+/// and the player's inventory. Returns the number of turns spent.
+pub fn search_location(location: container::Location, transfers: &[Transfer],
+                       game: &mut Game, actors: &mut [Object]) -> i32 {
+    let (x, y) = actors[consts::PLAYER].pos();
+    let scavenging = character::level(&actors[consts::PLAYER],
+                                      Skill::Scavenging);
+    let (name, turns, noise, first_search) = {
+        let object = container_at(location, &mut game.map, actors);
+        let container = object.container.as_mut().unwrap();
+        if container.searched {
+            (object.name.clone(), 1, 0, false)
+        } else {
+            container.searched = true;
+            (object.name.clone(), container.search_turns, container.noise,
+             true)
+        }
+    };
+    if first_search {
+        character::practice(&mut actors[consts::PLAYER], Skill::Scavenging,
+                            turns, &mut game.log);
+    }
+    // An experienced scavenger knows where the good stuff is kept
+    let turns = cmp::max(1, turns - scavenging / 4);
+    if noise > 0 {
+        game.log.info(format!("You rummage through the {}.", name));
+        noise::make_noise(&mut game.noises, x, y, noise);
+    }
+
+    let mut contents = container_at(location, &mut game.map, actors)
+        .inventory.take().unwrap_or_else(Vec::new);
+    let mut inventory = actors[consts::PLAYER].inventory.take()
+        .unwrap_or_else(Vec::new);
+    for &transfer in transfers {
+        move_item(transfer, &name, &mut contents, &mut inventory,
+                  &mut game.log);
+    }
+    let capacity = carry_capacity(&actors[consts::PLAYER]);
+    warn_if_overburdened(&inventory, capacity, &mut game.log);
+    actors[consts::PLAYER].inventory = Some(inventory);
+    container_at(location, &mut game.map, actors).inventory = Some(contents);
+    turns
+}
+
+fn move_item(transfer: Transfer, name: &str, contents: &mut Vec<Object>,
+             inventory: &mut Vec<Object>, log: &mut log::Messages) {
+    match transfer {
+        Transfer::Take(item_id) => {
+            let item = contents.remove(item_id);
+            log.info(format!("You take {} from the {}.",
+                             item.display_name(), name));
+            add_to_inventory(item, inventory);
+        }
+        Transfer::Put(item_id) => {
+            unequip(item_id, inventory, log);
+            let item = inventory.remove(item_id);
+            log.info(format!("You put {} in the {}.",
+                             item.display_name(), name));
+            add_to_inventory(item, contents);
+        }
+    }
+}
+
+/// Let the player pick a recipe they have everything for.
+pub fn choose_recipe(recipes: &[Recipe], game_ui: &mut Ui, game: &mut Game,
+                     actors: &[Object]) -> Option<Command> {
+    if recipes.is_empty() {
+        game.log.alert("You can't think of anything to make.");
+        return None;
+    }
+    let inventory = match actors[consts::PLAYER].inventory {
+        Some(ref inventory) => inventory,
+        None => return None,
+    };
+    let recipe_id = match ui::crafting_menu(recipes, inventory, game_ui) {
+        Some(recipe_id) => recipe_id,
+        None => return None,
+    };
+    let missing = crafting::missing(&recipes[recipe_id], inventory);
+    if !missing.is_empty() {
+        game.log.alert(format!("To make {} you still need {}.",
+                               recipes[recipe_id].name, missing.join(", ")));
+        return None;
+    }
+    Some(Command::Craft(recipe_id))
+}
+
+/// Craft the recipe at `recipe_id` from the player's inventory. Returns
+/// the number of turns spent, 0 if nothing was made.
+pub fn player_craft(recipe_id: usize, data: &Data, game: &mut Game,
+                    actors: &mut [Object]) -> i32 {
+    let (x, y) = actors[consts::PLAYER].pos();
+    let inventory = match actors[consts::PLAYER].inventory {
+        Some(ref mut inventory) => inventory,
+        None => return 0,
+    };
+    let recipe = match data.recipes.get(recipe_id) {
+        Some(recipe) => recipe,
+        None => return 0,
+    };
+    if !crafting::missing(recipe, inventory).is_empty() {
+        return 0;
+    }
+
+    crafting::craft(recipe, inventory, &data.item_types, &mut game.log);
+    noise::make_noise(&mut game.noises, x, y, recipe.noise);
+    recipe.turns
+}
+
+/// Living survivors right next to (x, y) who are willing to talk.
+fn survivors_near(x: i32, y: i32, actors: &[Object]) -> Vec<usize> {
+    actors.iter().enumerate().filter(|&(id, object)| {
+        id != consts::PLAYER && object.alive &&
+            object.attitude.map_or(false, |a| a != Attitude::Hostile) &&
+            (object.x - x).abs() <= 1 && (object.y - y).abs() <= 1
+    }).map(|(id, _)| id).collect()
+}
+
+/// Pick a survivor next to the player and strike up a conversation.
+pub fn choose_talk_partner(dialogues: &[Dialogue], game_ui: &mut Ui,
+                           game: &mut Game, actors: &[Object])
+                           -> Option<Command> {
+    let (x, y) = actors[consts::PLAYER].pos();
+    let nearby = survivors_near(x, y, actors);
+    let npc_id = match nearby.len() {
+        0 => {
+            game.log.info("There is nobody here to talk to.");
+            return None;
+        }
+        1 => nearby[0],
+        _ => {
+            let names: Vec<_> = nearby.iter()
+                .map(|&id| actors[id].name.clone()).collect();
+            match ui::talk_menu(&names, game_ui) {
+                Some(choice) => nearby[choice],
+                None => return None,
+            }
+        }
+    };
+    choose_talk(npc_id, dialogues, game_ui, game, actors)
+}
+
+/// Hold a conversation with the survivor at `npc_id`, following their
+/// dialogue tree and settling on any trades along the way.
+fn choose_talk(npc_id: usize, dialogues: &[Dialogue], game_ui: &mut Ui,
+               game: &mut Game, actors: &[Object]) -> Option<Command> {
+    let name = actors[npc_id].name.clone();
+    let conversation = actors[npc_id].dialogue.as_ref().and_then(|wanted| {
+        dialogues.iter().find(|dialogue| &dialogue.name == wanted)
+    });
+    let conversation = match conversation {
+        Some(conversation) => conversation,
+        None => {
+            game.log.info(format!("{} has nothing to say.", name));
+            return Some(Command::Talk(npc_id, vec![]));
+        }
+    };
+
+    let mut trades = vec![];
+    let mut node_id = "start".to_string();
+    while let Some(node) = conversation.node(&node_id) {
+        let choice = match ui::dialogue_menu(&name, node, game_ui) {
+            Some(choice) => &node.choices[choice],
+            None => break,
+        };
+        match (choice.outcome, choice.next.as_ref()) {
+            (Some(dialogue::Outcome::Trade), _) => {
+                trades = choose_trades(npc_id, game_ui, actors);
+                break;
+            }
+            (Some(dialogue::Outcome::Leave), _) | (None, None) => break,
+            (None, Some(next)) => node_id = next.clone(),
+        }
+    }
+    Some(Command::Talk(npc_id, trades))
+}
+
+/// Barter with the survivor at `npc_id`. The player picks something the
+/// survivor carries, then puts items from their own inventory on the
+/// table until the survivor thinks the swap is fair. Swaps are tried out
+/// on copies of both inventories until the conversation is carried out.
+fn choose_trades(npc_id: usize, game_ui: &mut Ui, actors: &[Object])
+                 -> Vec<Trade> {
+    let name = actors[npc_id].name.clone();
+    let markup = actors[npc_id].attitude.map_or(100, |a| a.markup());
+    let mut stock = actors[npc_id].inventory.clone().unwrap_or_else(Vec::new);
+    let mut inventory = actors[consts::PLAYER].inventory.clone()
+        .unwrap_or_else(Vec::new);
+    let mut scratch = vec![];
+    let mut trades = vec![];
+    loop {
+        let prices: Vec<i32> = stock.iter()
+            .map(|item| cmp::max(1, item.value * markup / 100)).collect();
+        let header = if stock.is_empty() {
+            format!("{} has nothing to trade.\n", name)
+        } else {
+            format!("{} has:\n", name)
+        };
+        let wanted = match ui::barter_menu(&header, &stock, &prices, None,
+                                           game_ui) {
+            Some(wanted) => wanted,
+            None => return trades,
+        };
+        let offered = choose_offer(game_ui, &stock[wanted], prices[wanted],
+                                   &inventory);
+        if let Some(offered) = offered {
+            let trade = Trade { offered: offered, wanted: wanted };
+            barter(&trade, &mut stock, &mut inventory, &mut scratch);
+            trades.push(trade);
+        }
+    }
+}
+
+/// Talk to the survivor at `npc_id`, making the `trades` agreed with them.
+pub fn talk_to(npc_id: usize, trades: &[Trade], game: &mut Game,
+               actors: &mut [Object]) {
+    if trades.is_empty() {
+        return;
+    }
+    let mut stock = actors[npc_id].inventory.take().unwrap_or_else(Vec::new);
+    let mut inventory = actors[consts::PLAYER].inventory.take()
+        .unwrap_or_else(Vec::new);
+    for trade in trades {
+        barter(trade, &mut stock, &mut inventory, &mut game.log);
+    }
+    let capacity = carry_capacity(&actors[consts::PLAYER]);
+    warn_if_overburdened(&inventory, capacity, &mut game.log);
+    actors[npc_id].inventory = Some(stock);
+    actors[consts::PLAYER].inventory = Some(inventory);
+}
+
+/// Hand over what the player offered in `trade` for the item they wanted.
+fn barter(trade: &Trade, stock: &mut Vec<Object>, inventory: &mut Vec<Object>,
+          log: &mut log::Messages) {
+    let mut given = vec![];
+    for inventory_id in (0..trade.offered.len()).rev() {
+        for _ in 0..trade.offered[inventory_id] {
+            unequip(inventory_id, inventory, log);
+            let item = take_one(inventory_id, inventory);
+            given.push(item.name.clone());
+            add_to_inventory(item, stock);
+        }
+    }
+    let item = take_one(trade.wanted, stock);
+    log.success(format!("You trade {} for {}.", given.join(", "), item.name));
+    add_to_inventory(item, inventory);
+}
+
+/// Let the player put items on the table for `wanted`. Returns how many
+/// of each inventory stack they offered once it covers `price`, or None
+/// if they back out.
+fn choose_offer(game_ui: &mut Ui, wanted: &Object, price: i32,
+                inventory: &[Object]) -> Option<Vec<i32>> {
+    let mut offered = vec![0; inventory.len()];
+    let values: Vec<i32> = inventory.iter().map(|item| item.value).collect();
+    loop {
+        let total: i32 = offered.iter().zip(&values)
+            .map(|(count, value)| count * value).sum();
+        if total >= price {
+            return Some(offered);
+        }
+        let header = format!("The {} costs {}. You are offering {}.\n\
+                              Pick something to add, or any other key to \
+                              back out.\n", wanted.name, price, total);
+        let choice = ui::barter_menu(&header, inventory, &values,
+                                     Some(&offered), game_ui);
+        match choice {
+            Some(id) if offered[id] < inventory[id].count => offered[id] += 1,
+            Some(_) => {}
+            None => return None,
+        }
     }
 }
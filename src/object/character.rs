@@ -0,0 +1,156 @@
+use std::cmp;
+
+use log::{self, MessageLog};
+use object::Object;
+
+/// Attribute score of an unremarkable person.
+pub const AVERAGE_ATTRIBUTE: i32 = 5;
+pub const MAX_SKILL_LEVEL: i32 = 10;
+
+/// Physical traits fixed when the character is created.
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Attributes {
+    // Steadier aim and quicker hands
+    pub agility: i32,
+    // Stamina to keep running
+    pub endurance: i32,
+    // Weight that can be carried without slowing down
+    pub strength: i32,
+}
+
+impl Attributes {
+    pub fn average() -> Self {
+        Attributes {
+            agility: AVERAGE_ATTRIBUTE,
+            endurance: AVERAGE_ATTRIBUTE,
+            strength: AVERAGE_ATTRIBUTE,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Skill {
+    FirstAid,
+    Melee,
+    Scavenging,
+    Stealth,
+    Throwing,
+}
+
+pub const SKILLS: [Skill; 5] = [Skill::FirstAid, Skill::Melee,
+                                Skill::Scavenging, Skill::Stealth,
+                                Skill::Throwing];
+
+impl Skill {
+    pub fn name(self) -> &'static str {
+        match self {
+            Skill::FirstAid => "First aid",
+            Skill::Melee => "Melee",
+            Skill::Scavenging => "Scavenging",
+            Skill::Stealth => "Stealth",
+            Skill::Throwing => "Throwing",
+        }
+    }
+}
+
+/// How good the character is at a skill, and how close to getting better.
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Training {
+    pub level: i32,
+    pub xp: i32,
+}
+
+impl Training {
+    /// Experience needed to reach the next level.
+    pub fn xp_to_next(&self) -> i32 {
+        10 * (self.level + 1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Character {
+    pub attributes: Attributes,
+    pub first_aid: Training,
+    pub melee: Training,
+    pub scavenging: Training,
+    pub stealth: Training,
+    pub throwing: Training,
+}
+
+impl Character {
+    pub fn new(attributes: Attributes) -> Self {
+        let untrained = Training { level: 0, xp: 0 };
+        Character {
+            attributes: attributes,
+            first_aid: untrained,
+            melee: untrained,
+            scavenging: untrained,
+            stealth: untrained,
+            throwing: untrained,
+        }
+    }
+
+    pub fn training(&self, skill: Skill) -> &Training {
+        match skill {
+            Skill::FirstAid => &self.first_aid,
+            Skill::Melee => &self.melee,
+            Skill::Scavenging => &self.scavenging,
+            Skill::Stealth => &self.stealth,
+            Skill::Throwing => &self.throwing,
+        }
+    }
+
+    pub fn training_mut(&mut self, skill: Skill) -> &mut Training {
+        match skill {
+            Skill::FirstAid => &mut self.first_aid,
+            Skill::Melee => &mut self.melee,
+            Skill::Scavenging => &mut self.scavenging,
+            Skill::Stealth => &mut self.stealth,
+            Skill::Throwing => &mut self.throwing,
+        }
+    }
+}
+
+/// Level of `skill` for `object`. Anything without a character sheet,
+/// like a zombie, counts as untrained.
+pub fn level(object: &Object, skill: Skill) -> i32 {
+    object.character.as_ref().map_or(0, |c| c.training(skill).level)
+}
+
+/// How far `object` is above or below average in an attribute.
+pub fn modifier<F>(object: &Object, attribute: F) -> i32
+    where F: Fn(&Attributes) -> i32 {
+    object.character.as_ref()
+        .map_or(0, |c| attribute(&c.attributes) - AVERAGE_ATTRIBUTE)
+}
+
+/// Give `object` experience in `skill` for using it, raising the level
+/// when enough has been gathered.
+pub fn practice(object: &mut Object, skill: Skill, xp: i32,
+                log: &mut log::Messages) {
+    if let Some(ref mut character) = object.character {
+        let training = character.training_mut(skill);
+        if training.level >= MAX_SKILL_LEVEL {
+            return;
+        }
+        training.xp += xp;
+        while training.level < MAX_SKILL_LEVEL &&
+            training.xp >= training.xp_to_next() {
+                training.xp -= training.xp_to_next();
+                training.level += 1;
+                log.success(format!("Your {} skill improves to {}!",
+                                    skill.name().to_lowercase(),
+                                    training.level));
+            }
+    }
+}
+
+/// Percent chance for `object` to land a blow in melee.
+pub fn melee_hit_chance(object: &Object) -> i32 {
+    if object.character.is_none() {
+        return 100;
+    }
+    let chance = 70 + level(object, Skill::Melee) * 3 +
+        modifier(object, |a| a.agility) * 2;
+    cmp::max(5, cmp::min(95, chance))
+}
@@ -0,0 +1,67 @@
+use rand::Rng;
+use rand::distributions::{IndependentSample, Weighted};
+
+use object::Object;
+use object::actor;
+use object::load::ObjectTypes;
+use util::owned_weighted_choice::OwnedWeightedChoice;
+use util::rng;
+
+/// Something that holds loot in its inventory and has to be searched
+/// before the player knows what is inside.
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Container {
+    pub searched: bool,
+    // Turns it takes to go through it the first time
+    pub search_turns: i32,
+    // How loud rummaging through it is
+    pub noise: i32,
+    // Most items drawn from the loot table when the map is made
+    pub loot_rolls: i32,
+    // Object types the loot is drawn from, with their relative chance
+    pub loot_table: Vec<(String, u32)>,
+}
+
+/// Where a container lives, since corpses stay in the actor list while
+/// furniture is stored on the map.
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Location {
+    Actor(usize),
+    Tile(i32, i32, usize),
+}
+
+/// One item moved between a container and the player's inventory.
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Transfer {
+    // Index into the container's inventory
+    Take(usize),
+    // Index into the player's inventory
+    Put(usize),
+}
+
+/// Fill `object`'s inventory from its loot table.
+pub fn fill(object: &mut Object, item_types: &ObjectTypes) {
+    let (rolls, loot_table) = match object.container {
+        Some(ref container) => (container.loot_rolls,
+                                container.loot_table.clone()),
+        None => return,
+    };
+    if rolls <= 0 || loot_table.iter().all(|&(_, weight)| weight == 0) {
+        return;
+    }
+
+    let weighted = loot_table.into_iter()
+        .map(|(object_type, weight)| Weighted { weight: weight,
+                                                item: object_type })
+        .collect();
+    let loot_choice = OwnedWeightedChoice::new(weighted);
+    let mut rng = rng::game_rng();
+    let inventory = object.inventory.get_or_insert(vec![]);
+    for _ in 0..rng.gen_range(0, rolls + 1) {
+        let object_type = loot_choice.ind_sample(&mut rng);
+        if let Some(mut randomizer) = item_types.create_randomizer(&object_type) {
+            actor::add_to_inventory(randomizer.get_class().create_object(),
+                                    inventory);
+        }
+    }
+}
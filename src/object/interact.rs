@@ -0,0 +1,57 @@
+use map::{self, Map};
+use object::{self, Object};
+
+/// Something the player can do to an object by walking into it. Each
+/// object class lists the ones that make sense for it.
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Interaction {
+    Attack,
+    Open,
+    Push,
+    Search,
+    Talk,
+}
+
+impl Interaction {
+    pub fn verb(self) -> &'static str {
+        match self {
+            Interaction::Attack => "Attack",
+            Interaction::Open => "Open",
+            Interaction::Push => "Push",
+            Interaction::Search => "Search",
+            Interaction::Talk => "Talk to",
+        }
+    }
+}
+
+/// Index of a closed door on the tile at (x, y), if there is one.
+pub fn door_at(x: i32, y: i32, map: &Map) -> Option<usize> {
+    map[x as usize][y as usize].items.iter().position(|item| {
+        item.interactions.contains(&Interaction::Open)
+    })
+}
+
+/// Swing a door open for good, letting anything through and letting
+/// light in.
+pub fn open_door(door: &mut Object) {
+    door.blocks = object::Blocks::No;
+    door.blocks_view = object::Blocks::No;
+    door.symbol = '\'';
+    door.interactions.retain(|&interaction| interaction != Interaction::Open);
+}
+
+/// Shove the item at `item_id` on the tile (x, y) one step in the
+/// direction (dx, dy). Returns false if something is in the way.
+pub fn push(x: i32, y: i32, item_id: usize, dx: i32, dy: i32, map: &mut Map,
+            actors: &[Object]) -> bool {
+    let (new_x, new_y) = (x + dx, y + dy);
+    if new_x < 0 || new_y < 0 || new_x >= map::FLOOR_WIDTH ||
+        new_y >= map::FLOOR_HEIGHT ||
+        map::is_blocked(new_x, new_y, map, actors) != object::Blocks::No {
+            return false;
+        }
+    let mut item = map[x as usize][y as usize].items.remove(item_id);
+    item.set_pos(new_x, new_y);
+    map[new_x as usize][new_y as usize].items.push(item);
+    true
+}
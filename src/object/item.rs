@@ -1,14 +1,29 @@
+use std::cmp;
+use std::fmt;
+
+use rand::Rng;
+
 use rustc_serialize;
 
-use tcod::input::{self, Event, KeyCode};
+use tcod::input::{Event, KeyCode};
+use tcod::map::Map as FovMap;
 
-use ai::Ai;
 use consts;
+use fire;
 use game::Game;
+use keymap::Keymap;
 use log::MessageLog;
 use map;
+use noise;
 use object::Object;
+use object::character::{self, Skill};
+use object::status;
+use sight::Sight;
 use ui::{render_all, Ui};
+use util::rng;
+
+const MOLOTOV_RADIUS: i32 = 2;
+const MOLOTOV_NOISE: i32 = 6;
 
 pub enum UseResult {
     UsedUp,
@@ -23,34 +38,103 @@ pub enum Function {
     Stun,
 }
 
-pub fn heal_player(_game_ui: &mut Ui, game: &mut Game,
-                   actors: &mut [Object]) -> UseResult {
+impl Function {
+    pub fn description(&self) -> &'static str {
+        match *self {
+            Function::Fireball => "Bursts into flames where it lands.",
+            Function::Heal => "Patches up some of your wounds.",
+            Function::Lightning => "Strikes the closest enemy in sight.",
+            Function::Stun => "Leaves an enemy stunned for a few turns.",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Slot {
+    MainHand,
+    OffHand,
+    Body,
+    Head,
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Slot::MainHand => "main hand",
+            Slot::OffHand => "off hand",
+            Slot::Body => "body",
+            Slot::Head => "head",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Something an actor can wear or wield. Equipment stays in the owner's
+/// inventory and is only marked as equipped, so dropping or using up the
+/// item takes it out of play the same way as any other item.
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Equipment {
+    pub slot: Slot,
+    pub equipped: bool,
+    pub power_bonus: i32,
+    pub defense_bonus: i32,
+    // What the wielder does to their target, e.g. "smashes"
+    pub verb: Option<String>,
+    // Number of hits left before the item breaks. None never breaks.
+    pub durability: Option<i32>,
+    pub break_message: Option<String>,
+    // Effect a damaging hit passes on, e.g. bleeding from a blade
+    pub inflicts: Option<status::Effect>,
+}
+
+/// A weapon that shoots. Each shot uses up one inventory item named
+/// `ammo` and makes a noise of `noise` volume where the shooter stands.
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Ranged {
+    pub ammo: String,
+    pub damage: i32,
+    pub range: i32,
+    pub noise: i32,
+}
+
+/// Percent chance to hit a target `distance` tiles away through `cover`
+/// tiles of partial cover.
+pub fn hit_chance(distance: f32, cover: i32) -> i32 {
+    let chance = 95 - (distance * 5.0) as i32 - cover * 25;
+    cmp::max(5, cmp::min(95, chance))
+}
+
+pub fn heal_player(_target: Option<(i32, i32)>, game: &mut Game,
+                   actors: &mut [Object], _sight: &Sight) -> UseResult {
     if let Some(fighter) = actors[consts::PLAYER].fighter {
         if fighter.hp == fighter.max_hp {
             game.log.alert( "You are already at full health.");
             return UseResult::Cancelled;
         }
         game.log.success( "Your wounds start to feel better!");
-        actors[consts::PLAYER].heal(3);
+        let first_aid = character::level(&actors[consts::PLAYER],
+                                         Skill::FirstAid);
+        actors[consts::PLAYER].heal(3 + first_aid);
+        character::practice(&mut actors[consts::PLAYER], Skill::FirstAid, 3,
+                            &mut game.log);
+        status::remove(&mut actors[consts::PLAYER], status::Kind::Bleeding,
+                       &mut game.log);
         return UseResult::UsedUp;
     }
     UseResult::Cancelled
 }
 
-pub fn cast_confuse(game_ui: &mut Ui, game: &mut Game,
-                    actors: &mut [Object]) -> UseResult {
-    game.log.info( "Left-click an enemy to confuse it, or right-click \
-                   to cancel.");
-    let monster_id = target_monster(game_ui, game, actors, Some(5.0));
+pub fn cast_confuse(target: Option<(i32, i32)>, game: &mut Game,
+                    actors: &mut [Object], _sight: &Sight) -> UseResult {
+    let monster_id = target.and_then(|(x, y)| monster_at(x, y, actors));
     if let Some(monster_id) = monster_id {
-        let old_ai = actors[monster_id].ai.take().unwrap_or(Ai::Basic);
-        actors[monster_id].ai = Some(Ai::Stunned {
-            previous_ai: Box::new(old_ai),
-            num_turns: 3,
-        });
         game.log.info(
             format!("The eyes of the {} look vacant and it starts to \
                      stumble around!", actors[monster_id].name));
+        status::apply(&mut actors[monster_id],
+                      status::Effect { kind: status::Kind::Stunned,
+                                       turns: 3, potency: 1 },
+                      &mut game.log);
         UseResult::UsedUp
     } else {
         game.log.alert( "No enemy is within range.");
@@ -58,52 +142,55 @@ pub fn cast_confuse(game_ui: &mut Ui, game: &mut Game,
     }
 }
 
-// pub fn stun(game_ui: &mut Ui, game: &mut Game,
-//             actors: &mut [Object]) -> UseResult {
-//     if let Some(monster_id) = monster_id {
-//         let old_ai = actors[monster_id].ai.take().unwrap_or(Ai::Basic);
-//         actors[monster_id].ai = Some(Ai::Stunned {
-//             previous_ai: Box::new(old_ai),
-//             num_turns: 3,
-//         });
-//         game.log.info(
-//             format!("The eyes of the {} look vacant and it starts to \
-//                      stumble around!", actors[monster_id].name));
-//         UseResult::UsedUp
-//     } else {
-//         game.log.alert( "No enemy is within range.");
-//         UseResult::Cancelled
-//     }
-// }
-
-pub fn cast_fireball(game_ui: &mut Ui, game: &mut Game,
-                     actors: &mut [Object]) -> UseResult {
-    game.log.info( "Left-click a target tile for the molotov, \
-                   or right-click to cancel.");
-    let (x, y) = match target_tile(game_ui, game, actors, None) {
+pub fn cast_fireball(target: Option<(i32, i32)>, game: &mut Game,
+                     actors: &mut [Object], _sight: &Sight) -> UseResult {
+    let (x, y) = match target {
         Some(tile_pos) => tile_pos,
         None => return UseResult::Cancelled,
     };
 
+    // A poor throw lands a tile or so off target
+    let distance = actors[consts::PLAYER].distance(x, y);
+    let chance = hit_chance(distance, 0) +
+        character::level(&actors[consts::PLAYER], Skill::Throwing) * 3 +
+        character::modifier(&actors[consts::PLAYER], |a| a.agility) * 2;
+    character::practice(&mut actors[consts::PLAYER], Skill::Throwing, 2,
+                        &mut game.log);
+    let (x, y) = if rng::game_rng().gen_range(0, 100) < chance {
+        (x, y)
+    } else {
+        game.log.alert("Your throw goes wide!");
+        (x + rng::game_rng().gen_range(-1, 2),
+         y + rng::game_rng().gen_range(-1, 2))
+    };
+
     game.log.success(
-        format!("The molotov explodes, burning everything within a {} \
-                 radius!", 5));
+        format!("The molotov explodes, setting everything within a {} \
+                 radius alight!", MOLOTOV_RADIUS));
+    noise::make_noise(&mut game.noises, x, y, MOLOTOV_NOISE);
 
+    for fire_x in x - MOLOTOV_RADIUS..x + MOLOTOV_RADIUS + 1 {
+        for fire_y in y - MOLOTOV_RADIUS..y + MOLOTOV_RADIUS + 1 {
+            if (fire_x - x).pow(2) + (fire_y - y).pow(2) <=
+                MOLOTOV_RADIUS.pow(2) {
+                    fire::ignite(fire_x, fire_y, &mut game.map);
+                }
+        }
+    }
     for obj in actors {
-        if obj.distance(x, y) <= 5.0 && obj.fighter.is_some() {
-            game.log.success(
-                format!("The {} gets burned for {} hit points.",
-                        obj.name, 10));
-            obj.take_damage(10, &mut game.log);
+        if obj.fighter.is_some() && fire::is_burning(obj.x, obj.y, &game.map) {
+            status::apply(obj, status::Effect { kind: status::Kind::Burning,
+                                                turns: 4, potency: 3 },
+                          &mut game.log);
         }
     }
     UseResult::UsedUp
 }
 
 
-pub fn cast_lightning(game_ui: &mut Ui, game: &mut Game,
-                      actors: &mut [Object]) -> UseResult {
-    let monster_id = closest_monster(10, actors, game_ui);
+pub fn cast_lightning(_target: Option<(i32, i32)>, game: &mut Game,
+                      actors: &mut [Object], sight: &Sight) -> UseResult {
+    let monster_id = closest_monster(10, actors, &sight.fov);
     if let Some(monster_id) = monster_id {
         game.log.success(
             format!("A lightning bolt strikes the {} with loud thunder! \
@@ -117,57 +204,91 @@ pub fn cast_lightning(game_ui: &mut Ui, game: &mut Game,
     }
 }
 
-fn target_tile(game_ui: &mut Ui, game: &mut Game, actors: &[Object],
-               max_range: Option<f32>)
-               -> Option<(i32, i32)> {
+/// Whether the player can aim at (x, y): it has to be in view and, if
+/// there is a `max_range`, close enough.
+fn can_target(x: i32, y: i32, game_ui: &Ui, actors: &[Object],
+              max_range: Option<f32>) -> bool {
+    game_ui.sight.fov.is_in_fov(x, y) &&
+        max_range.map_or(true, |range| {
+            actors[consts::PLAYER].distance(x, y) <= range
+        })
+}
+
+/// Let the player pick a tile, either by moving a cursor with the
+/// movement keys and pressing Enter or by left-clicking it. Escape or a
+/// right-click gives up.
+pub fn target_tile(keymap: &Keymap, game_ui: &mut Ui, game: &mut Game,
+                   actors: &[Object], max_range: Option<f32>)
+                   -> Option<(i32, i32)> {
+    let (mut x, mut y) = actors[consts::PLAYER].pos();
     loop {
-        game_ui.root.flush();
-        let event = input::check_for_event(input::KEY_PRESS |
-                                           input::MOUSE)
-            .map(|e| e.1);
-        let mut key = None;
-        match event {
-            Some(Event::Mouse(m)) => game_ui.mouse = m,
-            Some(Event::Key(k)) => key = Some(k),
-            None => {}
-        }
         render_all(game_ui, game, actors, false);
-
-        let (x, y) = (game_ui.mouse.cx as i32, game_ui.mouse.cy as i32);
-        let in_fov = (x < map::FLOOR_WIDTH) && (y < map::FLOOR_HEIGHT) &&
-            game_ui.fov.is_in_fov(x, y);
-        let in_range = max_range.map_or(true,
-                                        |range| actors[consts::PLAYER]
-                                        .distance(x, y) <= range);
-        if game_ui.mouse.lbutton_pressed && in_fov && in_range {
-            return Some((x, y))
+        let layout = game_ui.layout;
+        game_ui.root.set_background(layout.map_x + x, layout.map_y + y,
+                                    game_ui.theme.look_cursor);
+        game_ui.root.flush();
+        if game_ui.root.window_closed() {
+            return None;
         }
 
-        let escape = key.map_or(false, |k| k.code == KeyCode::Escape);
-        if game_ui.mouse.rbutton_pressed || escape {
-            return None
+        match game_ui.root.check_for_event() {
+            Some(Event::Mouse(m)) => {
+                game_ui.mouse = m;
+                // The cursor follows the mouse over the map
+                if let Some(tile) = layout.map_tile(m.cx as i32, m.cy as i32) {
+                    x = tile.0;
+                    y = tile.1;
+                }
+                if m.rbutton_pressed {
+                    return None;
+                }
+                if m.lbutton_pressed &&
+                    can_target(x, y, game_ui, actors, max_range) {
+                        return Some((x, y));
+                    }
+            }
+            Some(Event::Key(key)) => match (key.code, keymap.action_for(key)) {
+                (KeyCode::Escape, _) => return None,
+                (KeyCode::Enter, _) => {
+                    if can_target(x, y, game_ui, actors, max_range) {
+                        return Some((x, y));
+                    }
+                }
+                (_, Some(action)) => {
+                    if let Some((dx, dy)) = action.direction() {
+                        x = cmp::max(0, cmp::min(map::FLOOR_WIDTH - 1,
+                                                 x + dx));
+                        y = cmp::max(0, cmp::min(map::FLOOR_HEIGHT - 1,
+                                                 y + dy));
+                    }
+                }
+                _ => {}
+            },
+            None => {}
         }
     }
 }
 
-fn target_monster(game_ui: &mut Ui, game: &mut Game, actors: &[Object],
-                  max_range: Option<f32>) -> Option<usize> {
+pub fn target_monster(keymap: &Keymap, game_ui: &mut Ui, game: &mut Game,
+                      actors: &[Object], max_range: Option<f32>)
+                      -> Option<usize> {
     loop {
-        match target_tile(game_ui, game, actors, max_range) {
-            Some((x, y)) => {
-                for(id, obj) in actors.iter().enumerate() {
-                    if obj.pos() == (x, y) && obj.fighter.is_some() &&
-                        id != consts::PLAYER {
-                            return Some(id)
-                        }
-                }
-            }
+        match target_tile(keymap, game_ui, game, actors, max_range) {
+            Some((x, y)) => if let Some(id) = monster_at(x, y, actors) {
+                return Some(id)
+            },
             None => return None,
         }
     }
 }
 
-fn closest_monster(max_range: i32, actors: &mut [Object], game_ui: &Ui)
+fn monster_at(x: i32, y: i32, actors: &[Object]) -> Option<usize> {
+    actors.iter().enumerate().find(|&(id, obj)| {
+        obj.pos() == (x, y) && obj.fighter.is_some() && id != consts::PLAYER
+    }).map(|(id, _)| id)
+}
+
+fn closest_monster(max_range: i32, actors: &mut [Object], fov_map: &FovMap)
                    -> Option<usize> {
     let mut closest_enemy = None;
     let mut closest_dist = (max_range + 1) as f32;
@@ -176,7 +297,7 @@ fn closest_monster(max_range: i32, actors: &mut [Object], game_ui: &Ui)
         if (id != consts::PLAYER) &&
             object.fighter.is_some() &&
             object.ai.is_some() &&
-            game_ui.fov.is_in_fov(object.x, object.y) {
+            fov_map.is_in_fov(object.x, object.y) {
                 let dist = actors[consts::PLAYER].distance_to(object);
                 if dist < closest_dist {
                     closest_enemy = Some(id);
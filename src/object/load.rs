@@ -5,32 +5,46 @@ use std::fs::File;
 use std::error::Error;
 use std::io::Read;
 use std::collections::HashMap;
-use rand;
+use std::collections::hash_map;
 use rand::distributions::{Weighted, IndependentSample};
 
 use ai;
+use scheduler;
 use object;
 use object::item;
 use object::actor;
 use util::owned_weighted_choice::OwnedWeightedChoice;
+use util::rng;
 
 #[derive(Debug, RustcDecodable)]
 struct JsonObjectClass {
     ai: Option<ai::Ai>,
     alive: bool,
+    attitude: Option<object::actor::Attitude>,
+    background: Option<(u8, u8, u8)>,
     blocks: object::Blocks,
     blocks_view: object::Blocks,
     can_pick_up: bool,
     chance: u32,
     color: (u8, u8, u8),
+    container: Option<object::container::Container>,
     context: String,
     description: String,
+    dialogue: Option<String>,
+    equipment: Option<object::item::Equipment>,
     fighter: Option<object::actor::Fighter>,
+    flammability: Option<i32>,
     function: Option<object::item::Function>,
+    interactions: Option<Vec<object::interact::Interaction>>,
     inventory: Option<Vec<object::Object>>,
     name: String,
     object_type: String,
+    ranged: Option<object::item::Ranged>,
+    speed: Option<i32>,
+    stackable: Option<bool>,
     symbol: char,
+    value: Option<i32>,
+    weight: f32,
 }
 
 #[derive(Debug, RustcDecodable)]
@@ -68,15 +82,20 @@ impl ObjectTypes {
         self.by_type.get_mut(&object_type).unwrap().push(object_class.clone());
     }
 
-    pub fn get_class(&self, class_name: &str) -> object::ObjectClass {
-        let some_class = self.by_name.get(class_name).unwrap();
-        some_class.clone()
+    pub fn get_class(&self, class_name: &str) -> Option<object::ObjectClass> {
+        self.by_name.get(class_name).cloned()
+    }
+
+    pub fn classes(&self) -> hash_map::Values<String, object::ObjectClass> {
+        self.by_name.values()
     }
 
     pub fn create_randomizer(&self, type_name: &str) ->
         Option<ObjectRandomizer> {
         if let Some(classes) = self.by_type.get(type_name) {
-            return Some(ObjectRandomizer::new(classes));
+            if !classes.is_empty() {
+                return Some(ObjectRandomizer::new(classes));
+            }
         }
         None
     }
@@ -84,7 +103,7 @@ impl ObjectTypes {
 
 pub struct ObjectRandomizer {
     weighted_choice: OwnedWeightedChoice<object::ObjectClass>,
-    rng: rand::ThreadRng
+    rng: rng::GameRng
 }
 
 impl ObjectRandomizer {
@@ -94,7 +113,7 @@ impl ObjectRandomizer {
             weighted.push(Weighted{weight: class.chance,
                                    item: class.clone()});
         }
-        let mut rng = rand::thread_rng();
+        let mut rng = rng::game_rng();
         ObjectRandomizer{
             weighted_choice: OwnedWeightedChoice::new(weighted),
             rng: rng,
@@ -110,9 +129,9 @@ pub fn load_objects(filename: &str) ->
 {
 
     let mut json = String::new();
-    let mut file = File::open(filename).unwrap();
-    file.read_to_string(&mut json).unwrap();
-    let types_from_json: JsonObjectTypes = json::decode(&json).unwrap();
+    let mut file = try!{ File::open(filename) };
+    try!{ file.read_to_string(&mut json) };
+    let types_from_json: JsonObjectTypes = try!{ json::decode(&json) };
     let types = types_from_json.types;
     let classes = types_from_json.classes;
     let mut return_val = ObjectTypes::new(types);
@@ -122,19 +141,31 @@ pub fn load_objects(filename: &str) ->
         let new_class = object::ObjectClass{
             ai: class.ai,
             alive: class.alive,
+            attitude: class.attitude,
+            background: class.background.map(|(r, g, b)| Color::new(r, g, b)),
             can_pick_up: class.can_pick_up,
             chance: class.chance,
             blocks: class.blocks,
             blocks_view: class.blocks_view,
             color: color,
+            container: class.container,
             context: class.context,
             description: class.description,
+            dialogue: class.dialogue,
+            equipment: class.equipment,
             fighter: class.fighter,
+            flammability: class.flammability.unwrap_or(0),
             function: class.function,
+            interactions: class.interactions.unwrap_or_else(Vec::new),
             inventory: class.inventory,
             name: class.name,
             object_type: class.object_type.clone(),
+            ranged: class.ranged,
+            speed: class.speed.unwrap_or(scheduler::TURN),
+            stackable: class.stackable.unwrap_or(false),
             symbol: class.symbol,
+            value: class.value.unwrap_or(1),
+            weight: class.weight,
         };
         return_val.add_class(class.object_type, new_class);
     }
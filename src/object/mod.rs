@@ -1,15 +1,18 @@
 use tcod::colors::Color;
-use tcod::console::{
-    BackgroundFlag,
-    Console
-};
 
 pub mod actor;
+pub mod character;
+pub mod container;
+pub mod interact;
 pub mod item;
 pub mod load;
+pub mod status;
 
 use ai::Ai;
+use scheduler;
 use log::{self, MessageLog};
+use ui::backend::Surface;
+use ui::theme::Theme;
 
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Ord, Eq,
          RustcEncodable, RustcDecodable)]
@@ -29,19 +32,31 @@ pub enum ObjectCategory{
 pub struct ObjectClass {
     pub ai: Option<Ai>,
     pub alive: bool,
+    pub attitude: Option<actor::Attitude>,
+    pub background: Option<Color>,
     pub blocks: Blocks,
     pub blocks_view: Blocks,
     pub can_pick_up: bool,
     pub chance: u32,
     pub color: Color,
+    pub container: Option<container::Container>,
     pub context: String,
     pub description: String,
+    pub dialogue: Option<String>,
+    pub equipment: Option<item::Equipment>,
     pub fighter: Option<actor::Fighter>,
+    pub flammability: i32,
     pub function: Option<item::Function>,
+    pub interactions: Vec<interact::Interaction>,
     pub inventory: Option<Vec<Object>>,
     pub name: String,
     pub object_type: String,
+    pub ranged: Option<item::Ranged>,
+    pub speed: i32,
+    pub stackable: bool,
     pub symbol: char,
+    pub value: i32,
+    pub weight: f32,
 }
 
 impl ObjectClass {
@@ -49,16 +64,37 @@ impl ObjectClass {
         Object{
             ai: self.ai.clone(),
             alive: self.alive,
+            attitude: self.attitude,
+            background: self.background,
             blocks: self.blocks,
             blocks_view: self.blocks_view,
             can_pick_up: self.can_pick_up,
+            character: None,
             color: self.color,
+            container: self.container.clone(),
+            count: 1,
+            description: self.description.clone(),
+            dialogue: self.dialogue.clone(),
+            energy: 0,
+            equipment: self.equipment.clone(),
             fighter: self.fighter,
+            flammability: self.flammability,
             function: self.function,
+            horde: None,
+            interactions: self.interactions.clone(),
             inventory: self.inventory.clone(),
+            last_seen: None,
+            move_mode: actor::MoveMode::Walk,
             name: self.name.to_string(),
             object_type: self.object_type.to_string(),
+            ranged: self.ranged.clone(),
+            speed: self.speed,
+            stackable: self.stackable,
+            stamina: None,
+            status: vec![],
             symbol: self.symbol,
+            value: self.value,
+            weight: self.weight,
             x: 0,
             y: 0,
         }
@@ -69,16 +105,51 @@ impl ObjectClass {
 pub struct Object {
     pub ai: Option<Ai>,
     pub alive: bool,
+    // How a survivor feels about the player
+    pub attitude: Option<actor::Attitude>,
+    // Drawn behind the symbol, to tell similar looking things apart
+    pub background: Option<Color>,
     pub blocks: Blocks,
     pub blocks_view: Blocks,
     pub can_pick_up: bool,
+    // Attributes and skills, only the player has these
+    pub character: Option<character::Character>,
     pub color: Color,
+    pub container: Option<container::Container>,
+    // How many identical items this object stands for when stacked
+    pub count: i32,
+    pub description: String,
+    // Name of the conversation tree to use when talked to
+    pub dialogue: Option<String>,
+    // Banked time, spent on actions. See `scheduler`.
+    pub energy: i32,
+    pub equipment: Option<item::Equipment>,
     pub fighter: Option<actor::Fighter>,
+    // Chance out of 100 of catching fire, and how long it feeds one
+    pub flammability: i32,
     pub function: Option<item::Function>,
+    // Id of the horde this zombie roams with, see `horde`
+    pub horde: Option<usize>,
+    // What happens when the player bumps into this
+    pub interactions: Vec<interact::Interaction>,
     pub inventory: Option<Vec<Object>>,
+    // Where the player last saw this monster, marked on the map until
+    // they spot it again
+    pub last_seen: Option<(i32, i32)>,
+    pub move_mode: actor::MoveMode,
     pub name: String,
     pub object_type: String,
+    pub ranged: Option<item::Ranged>,
+    // Energy gained each tick
+    pub speed: i32,
+    pub stackable: bool,
+    pub stamina: Option<actor::Stamina>,
+    pub status: Vec<status::Effect>,
     pub symbol: char,
+    // What a single item is worth when trading
+    pub value: i32,
+    // Weight of a single item, in kilograms
+    pub weight: f32,
     pub x: i32,
     pub y: i32,
 }
@@ -91,29 +162,74 @@ impl Object {
         Object {
             ai: None,
             alive: false,
+            attitude: None,
+            background: None,
             blocks: blocks,
             blocks_view: blocks_view,
             can_pick_up: can_pick_up,
+            character: None,
             color: color,
+            container: None,
+            count: 1,
+            description: "".into(),
+            dialogue: None,
+            energy: 0,
+            equipment: None,
             fighter: None,
+            flammability: 0,
             function: None,
+            horde: None,
+            interactions: vec![],
             inventory: None,
+            last_seen: None,
+            move_mode: actor::MoveMode::Walk,
             name: name.into(),
             object_type: "".into(),
+            ranged: None,
+            speed: scheduler::TURN,
+            stackable: false,
+            stamina: None,
+            status: vec![],
             symbol: symbol,
+            value: 0,
+            weight: 0.0,
             x: x,
             y: y,
         }
     }
 
-    pub fn draw(&self, con: &mut Console) {
-        con.set_default_foreground(self.color);
-        con.put_char(self.x, self.y, self.symbol,
-                     BackgroundFlag::None);
+    /// Name for menus and messages, including the size of a stack.
+    pub fn display_name(&self) -> String {
+        if self.count > 1 {
+            format!("{} (x{})", self.name, self.count)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// Whether `other` can be merged into this object's stack.
+    pub fn stacks_with(&self, other: &Object) -> bool {
+        self.stackable && other.stackable && self.name == other.name &&
+            self.equipment.as_ref().map_or(true, |e| !e.equipped)
     }
 
-    pub fn clear(&self, con: &mut Console) {
-        con.put_char(self.x, self.y, ' ', BackgroundFlag::None);
+    pub fn total_weight(&self) -> f32 {
+        self.weight * self.count as f32
+    }
+
+    pub fn draw(&self, con: &mut Surface, theme: &Theme) {
+        let color = theme.object_color(self.color);
+        match self.background {
+            Some(background) => {
+                con.put_char_ex(self.x, self.y, self.symbol, color,
+                                theme.object_color(background))
+            }
+            None => con.put_char(self.x, self.y, self.symbol, color),
+        }
+    }
+
+    pub fn clear(&self, con: &mut Surface) {
+        con.put_char(self.x, self.y, ' ', self.color);
     }
 
     pub fn pos(&self) -> (i32, i32) {
@@ -163,17 +279,104 @@ impl Object {
         }
     }
 
+    /// All equipment currently worn or wielded from this object's inventory.
+    pub fn get_all_equipped(&self) -> Vec<&item::Equipment> {
+        match self.inventory {
+            Some(ref inventory) => inventory.iter()
+                .filter_map(|item| item.equipment.as_ref())
+                .filter(|equipment| equipment.equipped)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Index into the inventory of the item equipped in `slot`, if any.
+    pub fn get_equipped_in_slot(&self, slot: item::Slot) -> Option<usize> {
+        self.inventory.as_ref().and_then(|inventory| {
+            inventory.iter().position(|item| {
+                item.equipment.as_ref().map_or(false, |equipment| {
+                    equipment.equipped && equipment.slot == slot
+                })
+            })
+        })
+    }
+
+    pub fn power(&self) -> i32 {
+        let base_power = self.fighter.map_or(0, |f| f.power);
+        let bonus: i32 = self.get_all_equipped().iter()
+            .map(|e| e.power_bonus).sum();
+        base_power + bonus
+    }
+
+    pub fn defense(&self) -> i32 {
+        let base_defense = self.fighter.map_or(0, |f| f.defense);
+        let bonus: i32 = self.get_all_equipped().iter()
+            .map(|e| e.defense_bonus).sum();
+        base_defense + bonus
+    }
+
+    fn attack_verb(&self) -> String {
+        self.get_equipped_in_slot(item::Slot::MainHand)
+            .and_then(|id| {
+                self.inventory.as_ref().unwrap()[id].equipment.as_ref()
+                    .and_then(|equipment| equipment.verb.clone())
+            })
+            .unwrap_or_else(|| "attacks".into())
+    }
+
+    /// Wear down whatever is in the main hand after it has been used to
+    /// hit something, removing it from the inventory once it breaks.
+    fn wear_weapon(&mut self, log: &mut log::Messages) {
+        let weapon_id = match self.get_equipped_in_slot(item::Slot::MainHand) {
+            Some(id) => id,
+            None => return,
+        };
+        let owner = self.name.clone();
+        let inventory = self.inventory.as_mut().unwrap();
+        let broken = match inventory[weapon_id].equipment {
+            Some(item::Equipment { durability: Some(ref mut durability), .. }) => {
+                *durability -= 1;
+                *durability <= 0
+            }
+            _ => false,
+        };
+        if broken {
+            let weapon = inventory.remove(weapon_id);
+            let message = weapon.equipment.as_ref()
+                .and_then(|e| e.break_message.clone())
+                .unwrap_or_else(|| "breaks".into());
+            log.alert(format!("{}'s {} {}!", owner, weapon.name, message));
+        }
+    }
+
+    /// Effects a damaging hit from this object passes on: from its weapon
+    /// if it wields one, otherwise from its own body.
+    fn attack_effect(&self) -> Option<status::Effect> {
+        self.get_equipped_in_slot(item::Slot::MainHand)
+            .and_then(|id| {
+                self.inventory.as_ref().unwrap()[id].equipment.as_ref()
+                    .and_then(|equipment| equipment.inflicts)
+            })
+            .or_else(|| self.fighter.and_then(|f| f.inflicts))
+    }
+
     pub fn attack(&mut self, target: &mut Object, log: &mut log::Messages) {
-        let damage = self.fighter.map_or(0, |f| f.power) -
-            target.fighter.map_or(0, |f| f.defense);
+        let damage = self.power() - target.defense();
+        let verb = self.attack_verb();
         if damage > 0 {
-            log.info(format!("{} attacks {} for {} hit points.", self.name,
+            log.info(format!("{} {} {} for {} hit points.", self.name, verb,
                              target.name, damage));
             target.take_damage(damage, log);
+            if let Some(effect) = self.attack_effect() {
+                if target.alive {
+                    status::apply(target, effect, log);
+                }
+            }
         } else {
-            log.info(format!("{} attacks {} but whatevs!",
-                             self.name, target.name));
+            log.info(format!("{} {} {} but whatevs!",
+                             self.name, verb, target.name));
         }
+        self.wear_weapon(log);
     }
 
 }
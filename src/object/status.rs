@@ -0,0 +1,131 @@
+use std::cmp;
+
+use log::{self, MessageLog};
+use object::Object;
+use object::actor;
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Kind {
+    Stunned,
+    Bleeding,
+    Burning,
+    Infected,
+    Poisoned,
+    Exhausted,
+}
+
+/// What happens when an effect is applied to something already under it.
+enum Stacking {
+    // Keep the longer of the two durations
+    Refresh,
+    // Add the durations together
+    Extend,
+    // Add the potencies together and keep the longer duration
+    Intensify,
+}
+
+impl Kind {
+    fn stacking(self) -> Stacking {
+        match self {
+            Kind::Stunned | Kind::Burning | Kind::Exhausted |
+            Kind::Infected => Stacking::Refresh,
+            Kind::Poisoned => Stacking::Extend,
+            Kind::Bleeding => Stacking::Intensify,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Stunned => "stunned",
+            Kind::Bleeding => "bleeding",
+            Kind::Burning => "burning",
+            Kind::Infected => "infected",
+            Kind::Poisoned => "poisoned",
+            Kind::Exhausted => "exhausted",
+        }
+    }
+
+    /// Damage done on the given turn of the effect.
+    fn damage(self, potency: i32, turns_left: i32) -> i32 {
+        match self {
+            Kind::Bleeding | Kind::Burning | Kind::Poisoned => potency,
+            // The infection works slowly, over the whole of its course
+            Kind::Infected if turns_left % 10 == 0 => potency,
+            Kind::Infected | Kind::Stunned | Kind::Exhausted => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Effect {
+    pub kind: Kind,
+    pub turns: i32,
+    // How strong the effect is, e.g. damage per turn
+    pub potency: i32,
+}
+
+pub fn has(object: &Object, kind: Kind) -> bool {
+    object.status.iter().any(|effect| effect.kind == kind)
+}
+
+pub fn apply(object: &mut Object, effect: Effect, log: &mut log::Messages) {
+    match object.status.iter().position(|e| e.kind == effect.kind) {
+        Some(id) => {
+            let existing = &mut object.status[id];
+            match effect.kind.stacking() {
+                Stacking::Refresh => {
+                    existing.turns = cmp::max(existing.turns, effect.turns);
+                    existing.potency = cmp::max(existing.potency, effect.potency);
+                }
+                Stacking::Extend => {
+                    existing.turns += effect.turns;
+                    existing.potency = cmp::max(existing.potency, effect.potency);
+                }
+                Stacking::Intensify => {
+                    existing.turns = cmp::max(existing.turns, effect.turns);
+                    existing.potency += effect.potency;
+                }
+            }
+        }
+        None => {
+            log.status_change(format!("The {} is {}!", object.name,
+                                      effect.kind.name()));
+            object.status.push(effect);
+        }
+    }
+}
+
+pub fn remove(object: &mut Object, kind: Kind, log: &mut log::Messages) {
+    if has(object, kind) {
+        object.status.retain(|effect| effect.kind != kind);
+        log.status_change(format!("The {} is no longer {}.", object.name,
+                                  kind.name()));
+    }
+}
+
+/// Let one turn pass for every effect on `object`, doing their damage and
+/// dropping the ones that have run out.
+pub fn tick(object: &mut Object, log: &mut log::Messages) {
+    if object.status.is_empty() {
+        return;
+    }
+    let mut damage = 0;
+    for effect in object.status.iter_mut() {
+        effect.turns -= 1;
+        damage += effect.kind.damage(effect.potency, effect.turns);
+    }
+    let expired: Vec<Kind> = object.status.iter()
+        .filter(|effect| effect.turns <= 0)
+        .map(|effect| effect.kind).collect();
+    for kind in expired {
+        if kind == Kind::Infected && object.alive && object.attitude.is_some() {
+            // A bitten survivor doesn't get better
+            actor::turn_into_zombie(object, log);
+        } else {
+            remove(object, kind, log);
+        }
+    }
+    if damage > 0 && object.alive {
+        object.take_damage(damage, log);
+    }
+}
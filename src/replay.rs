@@ -0,0 +1,221 @@
+use rustc_serialize::json;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use tcod::input::{Event, KeyCode};
+
+use command::Command;
+use consts;
+use game::{self, Data, Game};
+use object::Object;
+use sight::{self, Sight};
+use ui;
+use ui::backend::{self, Screen};
+use util::rng;
+
+/// Where the run being played is recorded. Each new or continued game
+/// starts it over.
+pub const REPLAY_FILE: &'static str = "replay";
+
+const DEFAULT_SPEED: u64 = 10;
+
+/// How a recorded run began.
+#[derive(Debug, RustcEncodable, RustcDecodable)]
+pub enum Start {
+    // A new character's name and background
+    NewGame(String, String),
+    // A whole saved game, as it was loaded
+    SavedGame(String),
+}
+
+/// The first line of a replay file. Every line after it is one command.
+#[derive(Debug, RustcEncodable, RustcDecodable)]
+struct Header {
+    seed: u32,
+    start: Start,
+}
+
+pub struct Replay {
+    pub seed: u32,
+    pub start: Start,
+    pub commands: Vec<Command>,
+}
+
+/// Writes out a run command by command as it is played, so that even a
+/// crash leaves a replay behind.
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    pub fn create(filename: &str, seed: u32, start: Start)
+                  -> Result<Recorder, Box<Error>> {
+        let mut file = try!{ File::create(filename) };
+        let header = try!{ json::encode(&Header { seed: seed, start: start }) };
+        try!{ writeln!(file, "{}", header) };
+        Ok(Recorder { file: file })
+    }
+
+    pub fn record(&mut self, command: &Command) -> Result<(), Box<Error>> {
+        let line = try!{ json::encode(command) };
+        try!{ writeln!(self.file, "{}", line) };
+        Ok(())
+    }
+}
+
+pub fn load_replay(filename: &str) -> Result<Replay, Box<Error>> {
+    let file = try!{ File::open(filename) };
+    let mut lines = BufReader::new(file).lines();
+    let header: Header = match lines.next() {
+        Some(line) => try!{ json::decode(&try!{ line }) },
+        None => return Err(From::from("the replay is empty")),
+    };
+    let mut commands = vec![];
+    for line in lines {
+        let line = try!{ line };
+        // A crash can leave the last line half written
+        match json::decode(&line) {
+            Ok(command) => commands.push(command),
+            Err(_) => break,
+        }
+    }
+    Ok(Replay { seed: header.seed, start: header.start, commands: commands })
+}
+
+/// Set up the game as it was when the recording started.
+fn start(replay: &Replay, data: &Data)
+         -> Result<(Vec<Object>, Game), Box<Error>> {
+    rng::seed(replay.seed);
+    match replay.start {
+        Start::NewGame(ref name, ref background_name) => {
+            match data.backgrounds.iter().find(|b| &b.name == background_name) {
+                Some(background) => Ok(game::new_game(name, background,
+                                                      data)),
+                None => Err(From::from(format!("no background called {}",
+                                               background_name))),
+            }
+        }
+        Start::SavedGame(ref save) => game::decode_save(save),
+    }
+}
+
+/// Run every command as fast as possible without opening a window, then
+/// report how the run ended.
+pub fn play_headless(replay: &Replay) -> Result<(), Box<Error>> {
+    let data = try!{ game::load_data() };
+    let (mut actors, mut game) = try!{ start(replay, &data) };
+    let mut sight = Sight::new();
+    sight::refresh(&game.map, &actors, &mut sight);
+    for command in &replay.commands {
+        sight::compute(&mut game.map, &mut actors, &mut sight);
+        game::perform(command, &mut game, &data, &mut actors, &mut sight);
+    }
+
+    let player = &actors[consts::PLAYER];
+    println!("Replayed {} commands.", replay.commands.len());
+    println!("{} is {} at ({}, {}) with {} hit points.", player.name,
+             if player.alive { "alive" } else { "dead" }, player.x, player.y,
+             player.fighter.map_or(0, |f| f.hp));
+    for &(ref message, _) in game.log.iter().rev().take(10).rev() {
+        println!("  {}", message);
+    }
+    Ok(())
+}
+
+/// Show the run in a window at `speed` commands a second. Escape stops
+/// the playback early.
+pub fn play_on_screen(replay: &Replay, speed: u64, kind: backend::Kind)
+                      -> Result<(), Box<Error>> {
+    let data = try!{ game::load_data() };
+    let (mut actors, mut game) = try!{ start(replay, &data) };
+    let mut game_ui = try!{ ui::initialize("RustWorld replay", kind) };
+    ui::initialize_fov(&game.map, &actors, &mut game_ui);
+    for command in &replay.commands {
+        if game_ui.root.window_closed() || escape_pressed(&mut *game_ui.root) {
+            return Ok(());
+        }
+        sight::compute(&mut game.map, &mut actors, &mut game_ui.sight);
+        ui::render_all(&mut game_ui, &mut game, &actors, true);
+        game_ui.root.flush();
+        ui::clear_actors(&mut game_ui, &game, &actors);
+        let floor = game.past_floors.len();
+        game::perform(command, &mut game, &data, &mut actors,
+                      &mut game_ui.sight);
+        if game.past_floors.len() != floor {
+            ui::initialize_fov(&game.map, &actors, &mut game_ui);
+        }
+        thread::sleep(Duration::from_millis(1000 / speed));
+    }
+
+    sight::compute(&mut game.map, &mut actors, &mut game_ui.sight);
+    ui::render_all(&mut game_ui, &mut game, &actors, true);
+    game_ui.root.flush();
+    wait_for_key(&mut *game_ui.root);
+    Ok(())
+}
+
+fn escape_pressed(root: &mut Screen) -> bool {
+    match root.check_for_event() {
+        Some(Event::Key(key)) => key.code == KeyCode::Escape,
+        _ => false,
+    }
+}
+
+fn wait_for_key(root: &mut Screen) {
+    while !root.window_closed() {
+        if let Some(Event::Key(_)) = root.check_for_event() {
+            return;
+        }
+        root.flush();
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: rustworld --replay [FILE] [--headless] [--speed N] \
+               [--terminal]\n\n\
+               Plays back a recorded run, {} by default. --headless runs it \
+               without a window and prints how it ended. --speed sets how \
+               many commands are shown a second, {} by default. --terminal \
+               shows it in the terminal instead of a window.",
+              REPLAY_FILE, DEFAULT_SPEED);
+    process::exit(1);
+}
+
+/// Play back a replay as asked on the command line.
+pub fn run(args: &[String], kind: backend::Kind) {
+    let mut filename = REPLAY_FILE.to_string();
+    let mut headless = false;
+    let mut speed = DEFAULT_SPEED;
+    let mut args = args.iter();
+    match args.next() {
+        Some(arg) if arg == "--replay" => {}
+        _ => usage(),
+    }
+    while let Some(arg) = args.next() {
+        match &arg[..] {
+            "--headless" => headless = true,
+            "--speed" => speed = match args.next().map(|n| n.parse()) {
+                Some(Ok(n)) if n > 0 => n,
+                _ => usage(),
+            },
+            _ if !arg.starts_with("--") => filename = arg.clone(),
+            _ => usage(),
+        }
+    }
+
+    let result = load_replay(&filename).and_then(|replay| {
+        if headless {
+            play_headless(&replay)
+        } else {
+            play_on_screen(&replay, speed, kind)
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("Couldn't play {}: {}", filename, e);
+        process::exit(1);
+    }
+}
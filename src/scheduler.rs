@@ -0,0 +1,111 @@
+use tcod::map::Map as FovMap;
+
+use ai;
+use consts;
+use fire;
+use horde;
+use log::MessageLog;
+use game::Game;
+use object::Object;
+use object::actor::{self, MoveMode};
+use object::status::{self, Kind};
+
+/// Energy an actor needs banked before it can act. It is also what a
+/// normal action costs and what a normal-speed actor regains each tick.
+pub const TURN: i32 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Attack,
+    Craft(i32),
+    Move,
+    OpenDoor,
+    Push,
+    // Searching a container, lasting the given number of turns
+    Search(i32),
+    Shoot,
+    Talk,
+    Throw,
+    UseItem,
+    Wait,
+}
+
+/// Energy an action costs anyone doing it.
+pub fn cost(action: Action) -> i32 {
+    match action {
+        Action::Attack => TURN,
+        Action::Craft(turns) => turns * TURN,
+        Action::Move => TURN,
+        Action::OpenDoor => TURN / 2,
+        Action::Push => TURN * 2,
+        Action::Search(turns) => turns * TURN,
+        Action::Shoot => TURN,
+        Action::Talk => TURN,
+        Action::Throw => TURN,
+        Action::UseItem => TURN,
+        Action::Wait => TURN,
+    }
+}
+
+/// Energy an action costs the player, whose steps depend on how they are
+/// moving and what they carry.
+pub fn player_cost(action: Action, player: &Object) -> i32 {
+    match action {
+        Action::Move => {
+            let move_cost = match player.move_mode {
+                MoveMode::Sneak => cost(action) * 2,
+                MoveMode::Walk => cost(action),
+                MoveMode::Sprint => cost(action) / 2,
+            };
+            if actor::is_overburdened(player) {
+                move_cost * 2
+            } else {
+                move_cost
+            }
+        }
+        _ => cost(action),
+    }
+}
+
+/// Let time pass until the player has the energy to act again. Every
+/// other actor with an AI gains energy at its own speed along the way and
+/// acts as often as it can afford to. Status effects tick once for every
+/// turn that goes by, and a stunned player loses their turns until it
+/// wears off.
+pub fn advance(game: &mut Game, actors: &mut [Object], fov_map: &FovMap) {
+    loop {
+        while actors[consts::PLAYER].alive &&
+            actors[consts::PLAYER].energy < TURN {
+                tick(game, actors, fov_map);
+            }
+        if actors[consts::PLAYER].alive &&
+            status::has(&actors[consts::PLAYER], Kind::Stunned) {
+                game.log.alert("You are too stunned to act.");
+                actors[consts::PLAYER].energy -= TURN;
+                continue;
+            }
+        break;
+    }
+}
+
+fn tick(game: &mut Game, actors: &mut [Object], fov_map: &FovMap) {
+    fire::tick(&mut game.map, actors, &mut game.log);
+    horde::tick(&mut game.hordes, &game.map, actors);
+    for id in 0..actors.len() {
+        status::tick(&mut actors[id], &mut game.log);
+        if id == consts::PLAYER || actors[id].ai.is_some() {
+            actors[id].energy += actors[id].speed;
+        }
+    }
+    for id in 0..actors.len() {
+        while id != consts::PLAYER && actors[id].ai.is_some() &&
+            actors[id].energy >= TURN {
+                let action = if status::has(&actors[id], Kind::Stunned) {
+                    Action::Wait
+                } else {
+                    ai::take_turn(id, game, actors, fov_map)
+                };
+                actors[id].energy -= cost(action);
+            }
+    }
+}
@@ -0,0 +1,264 @@
+use rustc_serialize::json;
+use std::cmp;
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Write};
+
+use tcod::console::{FontLayout, FontType};
+
+use consts;
+use map;
+
+/// Where the player's choices from the options menu are kept.
+pub const SETTINGS_FILE: &'static str = "settings.json";
+/// Fonts and tilesets the options menu offers.
+pub const FONTS_FILE: &'static str = "data/fonts.json";
+
+/// Window sizes on offer, in cells. The smallest still fits a whole floor
+/// above the panel.
+pub const SCREEN_SIZES: [(i32, i32); 4] = [(60, 40), (80, 50), (100, 60),
+                                           (120, 70)];
+
+/// Rows the panel needs for the player's stats.
+const MIN_PANEL_HEIGHT: i32 = 7;
+
+/// How the glyphs are laid out in a font image.
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable)]
+pub enum GlyphLayout {
+    AsciiInCol,
+    AsciiInRow,
+    Tcod,
+}
+
+impl GlyphLayout {
+    pub fn tcod(self) -> FontLayout {
+        match self {
+            GlyphLayout::AsciiInCol => FontLayout::AsciiInCol,
+            GlyphLayout::AsciiInRow => FontLayout::AsciiInRow,
+            GlyphLayout::Tcod => FontLayout::Tcod,
+        }
+    }
+}
+
+/// A font or tileset image to draw the game with.
+#[derive(Clone, Debug, RustcDecodable)]
+pub struct Font {
+    pub file: String,
+    // Drawn in shades of grey and tinted, rather than in its own colors
+    pub greyscale: bool,
+    pub layout: GlyphLayout,
+    pub name: String,
+}
+
+impl Font {
+    /// The font the game ships with, for when the fonts file can't be used.
+    pub fn new() -> Self {
+        Font {
+            file: "bluebox.png".into(),
+            greyscale: true,
+            layout: GlyphLayout::AsciiInRow,
+            name: "Bluebox".into(),
+        }
+    }
+
+    pub fn font_type(&self) -> FontType {
+        if self.greyscale {
+            FontType::Greyscale
+        } else {
+            FontType::Default
+        }
+    }
+}
+
+#[derive(Debug, RustcDecodable)]
+struct JsonFonts {
+    fonts: Vec<Font>,
+}
+
+pub fn load_fonts(filename: &str) -> Result<Vec<Font>, Box<Error>> {
+    let mut json = String::new();
+    let mut file = try!{ File::open(filename) };
+    try!{ file.read_to_string(&mut json) };
+    let fonts: JsonFonts = try!{ json::decode(&json) };
+    if fonts.fonts.is_empty() {
+        return Err(format!("no fonts in {}", filename).into());
+    }
+    Ok(fonts.fonts)
+}
+
+/// Which edge of the screen the stats and messages go along.
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum PanelPosition {
+    Bottom,
+    Top,
+}
+
+impl PanelPosition {
+    pub fn name(self) -> &'static str {
+        match self {
+            PanelPosition::Bottom => "bottom",
+            PanelPosition::Top => "top",
+        }
+    }
+}
+
+#[derive(Clone, Debug, RustcEncodable, RustcDecodable)]
+pub struct Settings {
+    // File name of the font or tileset
+    pub font: String,
+    pub fullscreen: bool,
+    pub panel: PanelPosition,
+    pub screen_height: i32,
+    pub screen_width: i32,
+    // Name of the color theme, None for the default palette
+    pub theme: Option<String>,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Settings {
+            font: "bluebox.png".into(),
+            fullscreen: false,
+            panel: PanelPosition::Bottom,
+            screen_height: 50,
+            screen_width: 80,
+            theme: None,
+        }
+    }
+}
+
+pub fn load_settings(filename: &str) -> Result<Settings, Box<Error>> {
+    let mut json = String::new();
+    let mut file = try!{ File::open(filename) };
+    try!{ file.read_to_string(&mut json) };
+    let mut settings: Settings = try!{ json::decode(&json) };
+    // Anything smaller than the smallest size on offer can't fit the
+    // panel and the floor
+    let (min_width, min_height) = SCREEN_SIZES[0];
+    settings.screen_width = cmp::max(settings.screen_width, min_width);
+    settings.screen_height = cmp::max(settings.screen_height, min_height);
+    Ok(settings)
+}
+
+pub fn save_settings(settings: &Settings, filename: &str)
+                     -> Result<(), Box<Error>> {
+    let json = try!{ json::encode(settings) };
+    let mut file = try!{ File::create(filename) };
+    try!{ file.write_all(json.as_bytes()) };
+    Ok(())
+}
+
+/// Where each part of the screen goes at the chosen window size.
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    pub bar_width: i32,
+    // Top left corner of the map, and the room it has. The floor is drawn
+    // from the corner and anything past it is left blank.
+    pub map_height: i32,
+    pub map_width: i32,
+    pub map_x: i32,
+    pub map_y: i32,
+    pub msg_height: i32,
+    pub msg_width: i32,
+    pub msg_x: i32,
+    pub panel_height: i32,
+    pub panel_y: i32,
+    pub screen_height: i32,
+    pub screen_width: i32,
+}
+
+impl Layout {
+    pub fn new(settings: &Settings) -> Self {
+        Layout::sized(settings, settings.screen_width, settings.screen_height)
+    }
+
+    /// The layout for a screen `width` by `height`, which can differ from
+    /// the settings when the terminal decides the size.
+    pub fn sized(settings: &Settings, width: i32, height: i32) -> Self {
+        // Taller windows get a few more lines of messages
+        let panel_height = cmp::max(MIN_PANEL_HEIGHT, height / 7);
+        let (panel_y, map_y) = match settings.panel {
+            PanelPosition::Bottom => (height - panel_height, 0),
+            PanelPosition::Top => (0, panel_height),
+        };
+        let msg_x = consts::BAR_WIDTH + 2;
+        Layout {
+            bar_width: consts::BAR_WIDTH,
+            map_height: height - panel_height,
+            map_width: width,
+            map_x: 0,
+            map_y: map_y,
+            msg_height: panel_height - 1,
+            msg_width: width - msg_x,
+            msg_x: msg_x,
+            panel_height: panel_height,
+            panel_y: panel_y,
+            screen_height: height,
+            screen_width: width,
+        }
+    }
+
+    /// The map tile under the screen cell (x, y), if there is one.
+    pub fn map_tile(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (map_x, map_y) = (x - self.map_x, y - self.map_y);
+        if map_x >= 0 && map_y >= 0 &&
+            map_x < cmp::min(self.map_width, map::FLOOR_WIDTH) &&
+            map_y < cmp::min(self.map_height, map::FLOOR_HEIGHT) {
+                Some((map_x, map_y))
+            } else {
+                None
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use map;
+
+    use super::*;
+
+    #[test]
+    fn map_tile_with_the_panel_below() {
+        let layout = Layout::new(&Settings::new());
+        assert_eq!(layout.map_tile(0, 0), Some((0, 0)));
+        assert_eq!(layout.map_tile(5, 7), Some((5, 7)));
+        assert_eq!(layout.map_tile(-1, 0), None);
+        assert_eq!(layout.map_tile(0, -1), None);
+    }
+
+    #[test]
+    fn map_tile_with_the_panel_above() {
+        let mut settings = Settings::new();
+        settings.panel = PanelPosition::Top;
+        let layout = Layout::new(&settings);
+        assert_eq!(layout.map_tile(5, layout.panel_height), Some((5, 0)));
+        assert_eq!(layout.map_tile(5, layout.panel_height - 1), None);
+    }
+
+    #[test]
+    fn map_tile_stops_at_the_edge_of_the_floor() {
+        let layout = Layout::new(&Settings::new());
+        let (last_x, last_y) = (map::FLOOR_WIDTH - 1, map::FLOOR_HEIGHT - 1);
+        assert_eq!(layout.map_tile(last_x, last_y), Some((last_x, last_y)));
+        assert_eq!(layout.map_tile(map::FLOOR_WIDTH, 0), None);
+        assert_eq!(layout.map_tile(0, map::FLOOR_HEIGHT), None);
+    }
+
+    #[test]
+    fn sized_layout_fills_the_given_screen() {
+        let layout = Layout::sized(&Settings::new(), 90, 45);
+        assert_eq!((layout.screen_width, layout.screen_height), (90, 45));
+        assert_eq!(layout.map_width, 90);
+        assert_eq!(layout.map_height + layout.panel_height, 45);
+    }
+
+    #[test]
+    fn map_tile_stops_at_the_edge_of_the_map_area() {
+        let mut layout = Layout::new(&Settings::new());
+        layout.map_width = 10;
+        layout.map_height = 8;
+        assert_eq!(layout.map_tile(9, 7), Some((9, 7)));
+        assert_eq!(layout.map_tile(10, 0), None);
+        assert_eq!(layout.map_tile(0, 8), None);
+    }
+}
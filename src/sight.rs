@@ -0,0 +1,95 @@
+use tcod::map::{Map as FovMap, FovAlgorithm};
+
+use consts;
+use fire;
+use map::{self, Map, Remembered};
+use object::{self, Object};
+use object::actor;
+
+const FOV_ALGO: FovAlgorithm = FovAlgorithm::Shadow;
+const FOV_LIGHT_WALLS: bool = true;
+const TORCH_RADIUS: i32 = 3;
+
+/// What the player can see. `fov` is lit by their torch; `light` reaches
+/// as far as their line of sight goes and shows whatever fire lights up.
+/// Kept apart from the UI so a game can be played without a window.
+pub struct Sight {
+    pub fov: FovMap,
+    pub light: FovMap,
+}
+
+impl Sight {
+    pub fn new() -> Sight {
+        Sight {
+            fov: FovMap::new(map::FLOOR_WIDTH, map::FLOOR_HEIGHT),
+            light: FovMap::new(map::FLOOR_WIDTH, map::FLOOR_HEIGHT),
+        }
+    }
+}
+
+/// Bring the FOV maps up to date with doors, smoke and the like.
+pub fn refresh(map: &Map, actors: &[Object], sight: &mut Sight) {
+    for y in 0..map::FLOOR_HEIGHT {
+        for x in 0..map::FLOOR_WIDTH {
+            let transparent = map::blocks_view(x, y, map, actors) !=
+                object::Blocks::Full;
+            let walkable = map::is_blocked(x, y,  map, actors) ==
+                object::Blocks::No;
+            sight.fov.set(x, y, transparent, walkable);
+            sight.light.set(x, y, transparent, walkable);
+        }
+    }
+}
+
+/// Work out what the player sees from where they stand, and remember the
+/// tiles they have now laid eyes on along with what is on them. Monsters
+/// that slip out of view stay marked where the player last saw them.
+pub fn compute(map: &mut Map, actors: &mut [Object], sight: &mut Sight) {
+    {
+        let player = &actors[consts::PLAYER];
+        sight.fov.compute_fov(player.x, player.y, TORCH_RADIUS,
+                              FOV_LIGHT_WALLS, FOV_ALGO);
+        // fires light up anything in the player's line of sight
+        sight.light.compute_fov(player.x, player.y, 0, FOV_LIGHT_WALLS,
+                                FOV_ALGO);
+    }
+    for x in 0..map::FLOOR_WIDTH {
+        for y in 0..map::FLOOR_HEIGHT {
+            map[x as usize][y as usize].seen_monster = None;
+            if !is_visible(x, y, sight, map) {
+                continue;
+            }
+            let tile = &mut map[x as usize][y as usize];
+            tile.explored = true;
+            tile.seen_items = tile.items.iter().map(Remembered::of).collect();
+        }
+    }
+    for monster in actors.iter_mut() {
+        if !monster.alive || monster.ai.is_none() ||
+            !actor::is_hostile(monster) {
+                continue;
+            }
+        let (x, y) = monster.pos();
+        let in_view = is_visible(x, y, sight, map);
+        if in_view {
+            monster.last_seen = Some((x, y));
+        }
+        let (seen_x, seen_y) = match monster.last_seen {
+            Some(pos) => pos,
+            None => continue,
+        };
+        // An empty tile in plain view shows nothing, but the marker comes
+        // back once it is out of sight again
+        if in_view || !is_visible(seen_x, seen_y, sight, map) {
+            map[seen_x as usize][seen_y as usize].seen_monster =
+                Some(Remembered::of(monster));
+        }
+    }
+}
+
+/// Whether the player can see the tile, either by their torch or because
+/// a nearby fire lights it up.
+pub fn is_visible(x: i32, y: i32, sight: &Sight, map: &Map) -> bool {
+    sight.fov.is_in_fov(x, y) ||
+        (sight.light.is_in_fov(x, y) && fire::is_lit(x, y, map))
+}
@@ -0,0 +1,285 @@
+use std::cmp;
+use std::collections::VecDeque;
+
+use command::Command;
+use consts;
+use game::Game;
+use log::MessageLog;
+use map::{self, Map};
+use object::{self, Object};
+use object::actor;
+use object::interact;
+use sight::{self, Sight};
+
+/// Where the player is heading without being steered step by step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Destination {
+    // Whichever tile not seen yet is closest
+    Unexplored,
+    Tile(i32, i32),
+}
+
+/// Something the player has found that they might want to go back to.
+pub struct Feature {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+const DIRECTIONS: [(i32, i32); 8] = [(0, -1), (0, 1), (-1, 0), (1, 0),
+                                     (-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+fn in_bounds(x: i32, y: i32) -> bool {
+    x >= 0 && y >= 0 && x < map::FLOOR_WIDTH && y < map::FLOOR_HEIGHT
+}
+
+/// Whether the player knows they can walk over the tile. Closed doors are
+/// opened on the way.
+fn passable(x: i32, y: i32, map: &Map) -> bool {
+    map[x as usize][y as usize].explored &&
+        (map::is_blocked(x, y, map, &[]) == object::Blocks::No ||
+         interact::door_at(x, y, map).is_some())
+}
+
+/// The first step of the shortest walk over explored ground from the
+/// player to `destination`, or None if there is no way there.
+fn next_step(destination: Destination, map: &Map, actors: &[Object])
+             -> Option<(i32, i32)> {
+    let start = actors[consts::PLAYER].pos();
+    let width = map::FLOOR_WIDTH as usize;
+    let index = |x: i32, y: i32| x as usize + y as usize * width;
+    let mut came_from = vec![None; width * map::FLOOR_HEIGHT as usize];
+    let mut frontier = VecDeque::new();
+    came_from[index(start.0, start.1)] = Some(start);
+    frontier.push_back(start);
+
+    while let Some((x, y)) = frontier.pop_front() {
+        for &(dx, dy) in DIRECTIONS.iter() {
+            let (next_x, next_y) = (x + dx, y + dy);
+            if !in_bounds(next_x, next_y) ||
+                came_from[index(next_x, next_y)].is_some() {
+                    continue;
+                }
+            let goal = match destination {
+                // Heading for the unknown, the last explored tile on the
+                // way will do
+                Destination::Unexplored if (x, y) != start &&
+                    !map[next_x as usize][next_y as usize].explored =>
+                    Some((x, y)),
+                Destination::Tile(goal_x, goal_y) if
+                    (next_x, next_y) == (goal_x, goal_y) => {
+                        if passable(next_x, next_y, map) {
+                            came_from[index(next_x, next_y)] = Some((x, y));
+                            Some((next_x, next_y))
+                        } else if (x, y) != start {
+                            // Furniture can't be stood on, only beside
+                            Some((x, y))
+                        } else {
+                            None
+                        }
+                    }
+                _ => None,
+            };
+            if let Some(mut tile) = goal {
+                // Walk the path back to the step right after the start
+                while let Some(previous) = came_from[index(tile.0, tile.1)] {
+                    if previous == start {
+                        break;
+                    }
+                    tile = previous;
+                }
+                return Some((tile.0 - start.0, tile.1 - start.1));
+            }
+            if passable(next_x, next_y, map) {
+                came_from[index(next_x, next_y)] = Some((x, y));
+                frontier.push_back((next_x, next_y));
+            }
+        }
+    }
+    None
+}
+
+/// The first hostile actor the player can see, if any.
+fn monster_in_view(map: &Map, actors: &[Object], sight: &Sight)
+                   -> Option<usize> {
+    actors.iter().enumerate().find(|&(id, object)| {
+        id != consts::PLAYER && object.alive && object.ai.is_some() &&
+            actor::is_hostile(object) &&
+            sight::is_visible(object.x, object.y, sight, map)
+    }).map(|(id, _)| id)
+}
+
+/// The player's next step towards `destination`, or None once they should
+/// stop, in which case the log says why.
+pub fn next_command(destination: Destination, game: &mut Game,
+                    actors: &[Object], sight: &Sight) -> Option<Command> {
+    if !actors[consts::PLAYER].alive {
+        return None;
+    }
+    if let Some(id) = monster_in_view(&game.map, actors, sight) {
+        game.log.alert(format!("You spot a {} and stop.", actors[id].name));
+        return None;
+    }
+    let (dx, dy) = match next_step(destination, &game.map, actors) {
+        Some(step) => step,
+        None => {
+            let message = match destination {
+                Destination::Unexplored =>
+                    "There is nowhere left to explore that you can reach.",
+                Destination::Tile(x, y) if
+                    actors[consts::PLAYER].distance(x, y) < 2.0 =>
+                    "You have arrived.",
+                Destination::Tile(..) => "You don't know a way there.",
+            };
+            game.log.info(message);
+            return None;
+        }
+    };
+    let (x, y) = actors[consts::PLAYER].pos();
+    let in_the_way = actors.iter().any(|object| {
+        object.pos() == (x + dx, y + dy) && object.blocks != object::Blocks::No
+    });
+    if in_the_way {
+        game.log.info("Something is in the way.");
+        return None;
+    }
+    Some(Command::Move(dx, dy))
+}
+
+/// Places on the explored map worth travelling back to: the stairs and
+/// anything that can be searched, nearest to (from_x, from_y) first.
+pub fn features(map: &Map, from_x: i32, from_y: i32) -> Vec<Feature> {
+    let mut found = vec![];
+    for x in 0..map::FLOOR_WIDTH {
+        for y in 0..map::FLOOR_HEIGHT {
+            let tile = &map[x as usize][y as usize];
+            if !tile.explored {
+                continue;
+            }
+            for item in &tile.items {
+                if item.object_type == "stairs" || item.container.is_some() {
+                    found.push(Feature { name: item.name.clone(),
+                                         x: x, y: y });
+                }
+            }
+        }
+    }
+    found.sort_by_key(|feature| {
+        cmp::max((feature.x - from_x).abs(), (feature.y - from_y).abs())
+    });
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use tcod::colors;
+
+    use map::{self, Map, Tile};
+    use object::{Blocks, Object};
+    use object::interact::Interaction;
+
+    use super::*;
+
+    fn thing(name: &str, blocks: Blocks) -> Object {
+        Object::new(0, 0, '#', name, false, colors::WHITE, blocks, blocks)
+    }
+
+    /// A floor the player has already seen all of, with nothing on it.
+    fn explored_map() -> Map {
+        (0..map::FLOOR_WIDTH).map(|_| {
+            (0..map::FLOOR_HEIGHT).map(|_| Tile {
+                floor: thing("floor", Blocks::No),
+                explored: true,
+                fire: 0,
+                items: vec![],
+                seen_items: vec![],
+                seen_monster: None,
+                smoke: 0,
+            }).collect()
+        }).collect()
+    }
+
+    fn put(x: i32, y: i32, item: Object, map: &mut Map) {
+        map[x as usize][y as usize].items.push(item);
+    }
+
+    fn player_at(x: i32, y: i32) -> Vec<Object> {
+        let mut player = thing("player", Blocks::Full);
+        player.set_pos(x, y);
+        vec![player]
+    }
+
+    #[test]
+    fn steps_towards_the_goal() {
+        let map = explored_map();
+        let actors = player_at(5, 5);
+        assert_eq!(next_step(Destination::Tile(6, 5), &map, &actors),
+                   Some((1, 0)));
+        let step = next_step(Destination::Tile(9, 5), &map, &actors);
+        assert_eq!(step.map(|(dx, _)| dx), Some(1));
+    }
+
+    #[test]
+    fn goes_around_walls() {
+        let mut map = explored_map();
+        for y in 0..7 {
+            put(7, y, thing("wall", Blocks::Full), &mut map);
+        }
+        let actors = player_at(5, 5);
+        assert_eq!(next_step(Destination::Tile(9, 5), &map, &actors),
+                   Some((1, 1)));
+    }
+
+    #[test]
+    fn walks_through_closed_doors() {
+        let mut map = explored_map();
+        for y in 0..map::FLOOR_HEIGHT {
+            if y != 5 {
+                put(6, y, thing("wall", Blocks::Full), &mut map);
+            }
+        }
+        let mut door = thing("door", Blocks::Full);
+        door.interactions = vec![Interaction::Open];
+        put(6, 5, door, &mut map);
+        let actors = player_at(5, 5);
+        assert_eq!(next_step(Destination::Tile(7, 5), &map, &actors),
+                   Some((1, 0)));
+    }
+
+    #[test]
+    fn stops_beside_furniture() {
+        let mut map = explored_map();
+        put(8, 5, thing("table", Blocks::Full), &mut map);
+        let step = next_step(Destination::Tile(8, 5), &map, &player_at(6, 5));
+        assert_eq!(step.map(|(dx, _)| dx), Some(1));
+        assert_eq!(next_step(Destination::Tile(8, 5), &map, &player_at(7, 5)),
+                   None);
+    }
+
+    #[test]
+    fn heads_for_the_nearest_unexplored_tile() {
+        let mut map = explored_map();
+        for x in 10..map::FLOOR_WIDTH {
+            for y in 0..map::FLOOR_HEIGHT {
+                map[x as usize][y as usize].explored = false;
+            }
+        }
+        let step = next_step(Destination::Unexplored, &map, &player_at(5, 5));
+        assert_eq!(step.map(|(dx, _)| dx), Some(1));
+
+        let everything = explored_map();
+        assert_eq!(next_step(Destination::Unexplored, &everything,
+                             &player_at(5, 5)),
+                   None);
+    }
+
+    #[test]
+    fn no_way_out() {
+        let mut map = explored_map();
+        for &(dx, dy) in DIRECTIONS.iter() {
+            put(5 + dx, 5 + dy, thing("wall", Blocks::Full), &mut map);
+        }
+        assert_eq!(next_step(Destination::Tile(9, 5), &map, &player_at(5, 5)),
+                   None);
+    }
+}
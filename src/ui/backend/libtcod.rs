@@ -0,0 +1,99 @@
+use tcod;
+use tcod::colors::Color;
+use tcod::console::{BackgroundFlag, Console, Root};
+use tcod::input::{self, Event, Key};
+
+use consts;
+use settings::{Font, Settings};
+use ui::backend::{Cell, Screen, Surface};
+
+/// A libtcod window.
+pub struct TcodScreen {
+    root: Root,
+    title: String,
+}
+
+impl TcodScreen {
+    pub fn new(title: &str, settings: &Settings, fonts: &[Font]) -> Self {
+        tcod::system::set_fps(consts::LIMIT_FPS);
+        TcodScreen {
+            root: open_root(title, settings, fonts),
+            title: title.into(),
+        }
+    }
+}
+
+fn open_root(title: &str, settings: &Settings, fonts: &[Font]) -> Root {
+    let font = fonts.iter().find(|font| font.file == settings.font)
+        .unwrap_or(&fonts[0]);
+    Root::initializer()
+        .font(&font.file, font.layout.tcod())
+        .font_type(font.font_type())
+        .size(settings.screen_width, settings.screen_height)
+        .fullscreen(settings.fullscreen)
+        .title(title)
+        .init()
+}
+
+impl Surface for TcodScreen {
+    fn width(&self) -> i32 {
+        self.root.width()
+    }
+
+    fn height(&self) -> i32 {
+        self.root.height()
+    }
+
+    fn cell(&self, x: i32, y: i32) -> Cell {
+        if !self.in_bounds(x, y) {
+            return Cell::blank();
+        }
+        Cell {
+            background: self.root.get_char_background(x, y),
+            color: self.root.get_char_foreground(x, y),
+            glyph: self.root.get_char(x, y),
+        }
+    }
+
+    fn put_char(&mut self, x: i32, y: i32, glyph: char, color: Color) {
+        if self.in_bounds(x, y) {
+            self.root.set_char(x, y, glyph);
+            self.root.set_char_foreground(x, y, color);
+        }
+    }
+
+    fn set_background(&mut self, x: i32, y: i32, color: Color) {
+        if self.in_bounds(x, y) {
+            self.root.set_char_background(x, y, color, BackgroundFlag::Set);
+        }
+    }
+}
+
+impl Screen for TcodScreen {
+    fn flush(&mut self) {
+        self.root.flush();
+    }
+
+    fn check_for_event(&mut self) -> Option<Event> {
+        input::check_for_event(input::MOUSE | input::KEY_PRESS)
+            .map(|(_, event)| event)
+    }
+
+    fn wait_for_keypress(&mut self) -> Key {
+        self.root.wait_for_keypress(true)
+    }
+
+    fn window_closed(&self) -> bool {
+        self.root.window_closed()
+    }
+
+    fn draw_image(&mut self, filename: &str) {
+        let image = tcod::image::Image::from_file(filename)
+            .ok().expect("Background image not found");
+        tcod::image::blit_2x(&image, (0, 0), (-1, -1), &mut self.root, (0, 0));
+    }
+
+    fn apply_settings(&mut self, settings: &Settings, fonts: &[Font]) {
+        self.root = open_root(&self.title, settings, fonts);
+    }
+}
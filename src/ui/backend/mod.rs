@@ -0,0 +1,207 @@
+use std::error::Error;
+
+use tcod::colors::{self, Color};
+use tcod::input::{Event, Key};
+
+use settings::{self, Font, Settings};
+use ui::window;
+
+pub mod libtcod;
+pub mod terminal;
+
+/// Where the game is shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    // A libtcod window
+    Tcod,
+    // The terminal the game was started from, drawn with ANSI escape
+    // sequences
+    Terminal,
+}
+
+/// What one character cell of a surface shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub background: Color,
+    pub color: Color,
+    pub glyph: char,
+}
+
+impl Cell {
+    pub fn blank() -> Self {
+        Cell {
+            background: colors::BLACK,
+            color: colors::WHITE,
+            glyph: ' ',
+        }
+    }
+}
+
+/// A grid of cells to draw on. Drawing outside of it does nothing.
+pub trait Surface {
+    fn width(&self) -> i32;
+    fn height(&self) -> i32;
+    fn cell(&self, x: i32, y: i32) -> Cell;
+    /// Draw `glyph` in `color` at (x, y), keeping the background.
+    fn put_char(&mut self, x: i32, y: i32, glyph: char, color: Color);
+    fn set_background(&mut self, x: i32, y: i32, color: Color);
+
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width() && y < self.height()
+    }
+
+    fn put_char_ex(&mut self, x: i32, y: i32, glyph: char, color: Color,
+                   background: Color) {
+        self.put_char(x, y, glyph, color);
+        self.set_background(x, y, background);
+    }
+
+    /// Blank every cell, leaving `background` behind.
+    fn clear(&mut self, background: Color) {
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                self.put_char_ex(x, y, ' ', colors::WHITE, background);
+            }
+        }
+    }
+
+    fn fill_background(&mut self, x: i32, y: i32, width: i32, height: i32,
+                       color: Color) {
+        for cell_y in y..y + height {
+            for cell_x in x..x + width {
+                self.set_background(cell_x, cell_y, color);
+            }
+        }
+    }
+
+    /// Write `text` on one line starting at (x, y).
+    fn print(&mut self, x: i32, y: i32, text: &str, color: Color) {
+        for (offset, glyph) in text.chars().enumerate() {
+            self.put_char(x + offset as i32, y, glyph, color);
+        }
+    }
+
+    /// Write `text` on one line centered on (x, y).
+    fn print_centered(&mut self, x: i32, y: i32, text: &str, color: Color) {
+        let length = text.chars().count() as i32;
+        self.print(x - length / 2, y, text, color);
+    }
+
+    /// Write `text` wrapped to `width`, returning how many lines it took.
+    fn print_wrapped(&mut self, x: i32, y: i32, width: i32, text: &str,
+                     color: Color) -> i32 {
+        let lines = window::wrap(text, width as usize);
+        for (row, line) in lines.iter().enumerate() {
+            self.print(x, y + row as i32, line, color);
+        }
+        lines.len() as i32
+    }
+}
+
+/// Cells kept in memory, drawn on and then blitted onto the screen.
+#[derive(Clone, Debug)]
+pub struct Canvas {
+    cells: Vec<Cell>,
+    height: i32,
+    width: i32,
+}
+
+impl Canvas {
+    pub fn new(width: i32, height: i32) -> Self {
+        Canvas {
+            cells: vec![Cell::blank(); (width * height) as usize],
+            height: height,
+            width: width,
+        }
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if self.in_bounds(x, y) {
+            Some((y * self.width + x) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl Surface for Canvas {
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn cell(&self, x: i32, y: i32) -> Cell {
+        self.index(x, y).map_or(Cell::blank(), |id| self.cells[id])
+    }
+
+    fn put_char(&mut self, x: i32, y: i32, glyph: char, color: Color) {
+        if let Some(id) = self.index(x, y) {
+            self.cells[id].glyph = glyph;
+            self.cells[id].color = color;
+        }
+    }
+
+    fn set_background(&mut self, x: i32, y: i32, color: Color) {
+        if let Some(id) = self.index(x, y) {
+            self.cells[id].background = color;
+        }
+    }
+}
+
+/// Copy all of `source` onto `destination` with its top left corner at
+/// (x, y). Glyphs replace what was there, backgrounds are mixed in by
+/// `background_alpha`, from 0 for none of the source's to 1 for all.
+pub fn blit<S, D>(source: &S, destination: &mut D, x: i32, y: i32,
+                  background_alpha: f32)
+    where S: Surface + ?Sized, D: Surface + ?Sized
+{
+    for source_y in 0..source.height() {
+        for source_x in 0..source.width() {
+            let (to_x, to_y) = (x + source_x, y + source_y);
+            if !destination.in_bounds(to_x, to_y) {
+                continue;
+            }
+            let cell = source.cell(source_x, source_y);
+            let under = destination.cell(to_x, to_y).background;
+            destination.put_char_ex(to_x, to_y, cell.glyph, cell.color,
+                                    colors::lerp(under, cell.background,
+                                                 background_alpha));
+        }
+    }
+}
+
+/// The surface the player sees, along with the keyboard and mouse.
+pub trait Screen: Surface {
+    /// Show everything drawn so far, waiting as long as it takes to keep
+    /// to the frame rate.
+    fn flush(&mut self);
+    /// The next key press or mouse event, without waiting for one.
+    fn check_for_event(&mut self) -> Option<Event>;
+    fn wait_for_keypress(&mut self) -> Key;
+    fn window_closed(&self) -> bool;
+    /// Cover the screen with the picture in `filename`, where it can be
+    /// shown at all.
+    fn draw_image(&mut self, filename: &str);
+    /// Take on a new window size, font and so on.
+    fn apply_settings(&mut self, settings: &Settings, fonts: &[Font]);
+}
+
+/// Open the screen of the given kind as the settings say. A terminal is
+/// used at whatever size it already is, which has to fit the smallest
+/// screen size.
+pub fn open(kind: Kind, title: &str, settings: &Settings, fonts: &[Font])
+            -> Result<Box<Screen>, Box<Error>> {
+    match kind {
+        Kind::Tcod => Ok(Box::new(libtcod::TcodScreen::new(title, settings,
+                                                           fonts))),
+        Kind::Terminal => {
+            let (min_width, min_height) = settings::SCREEN_SIZES[0];
+            let terminal = try!{ terminal::Terminal::new(min_width,
+                                                         min_height) };
+            Ok(Box::new(terminal))
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use std::collections::VecDeque;
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tcod::colors::{self, Color};
+use tcod::input::{Event, Key, KeyCode};
+
+use consts;
+use settings::{Font, Settings};
+use ui::backend::{Canvas, Cell, Screen, Surface};
+
+const TTY: &'static str = "/dev/tty";
+
+// Switch to the alternate screen so the shell comes back untouched
+// afterwards, and hide the cursor
+const ENTER: &'static str = "\x1b[?1049h\x1b[?25l\x1b[2J";
+const LEAVE: &'static str = "\x1b[0m\x1b[?25h\x1b[?1049l";
+
+/// How long to wait between looks at the keyboard while waiting for a key.
+const POLL_INTERVAL_MS: u64 = 10;
+
+/// The terminal the game was started from, for playing without a window,
+/// e.g. over SSH. Colors are sent as 24 bit ANSI escape sequences and the
+/// keyboard is read raw. There is no mouse.
+pub struct Terminal {
+    closed: bool,
+    // What the terminal shows right now, to send only the cells that
+    // changed. None when it has to be drawn over from scratch.
+    drawn: Option<Canvas>,
+    // What is being drawn for the next flush
+    frame: Canvas,
+    input: File,
+    keys: VecDeque<Key>,
+    last_flush: Instant,
+    // The terminal's settings before the game changed them
+    saved_mode: String,
+}
+
+/// Run `stty` on the terminal with `args`, returning what it prints.
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = try!{ Command::new("stty").args(args)
+                       .stdin(Stdio::from(try!{ File::open(TTY) }))
+                       .output() };
+    if !output.status.success() {
+        return Err(io::Error::new(io::ErrorKind::Other,
+                                  "stty couldn't change the terminal"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The terminal's width and height in cells.
+fn size() -> io::Result<(i32, i32)> {
+    let size = try!{ stty(&["size"]) };
+    parse_size(&size).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::Other,
+                       "stty couldn't tell the terminal size")
+    })
+}
+
+/// Width and height from what `stty size` prints, rows first.
+fn parse_size(size: &str) -> Option<(i32, i32)> {
+    let mut numbers = size.split_whitespace().map(|n| n.parse::<i32>());
+    match (numbers.next(), numbers.next(), numbers.next()) {
+        (Some(Ok(rows)), Some(Ok(columns)), None) => Some((columns, rows)),
+        _ => None,
+    }
+}
+
+impl Terminal {
+    /// Take over the whole terminal, as long as it is at least
+    /// `min_width` by `min_height`.
+    pub fn new(min_width: i32, min_height: i32) -> io::Result<Self> {
+        let (width, height) = try!{ size() };
+        if width < min_width || height < min_height {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("the terminal is {}x{}, but the game needs at \
+                         least {}x{}", width, height, min_width,
+                        min_height)));
+        }
+        let saved_mode = try!{ stty(&["-g"]) };
+        // Keys come through one at a time as they are pressed, and reads
+        // return straight away when there are none
+        try!{ stty(&["raw", "-echo", "min", "0", "time", "0"]) };
+        let input = try!{ File::open(TTY) };
+        let mut stdout = io::stdout();
+        try!{ stdout.write_all(ENTER.as_bytes()) };
+        try!{ stdout.flush() };
+        Ok(Terminal {
+            closed: false,
+            drawn: None,
+            frame: Canvas::new(width, height),
+            input: input,
+            keys: VecDeque::new(),
+            last_flush: Instant::now(),
+            saved_mode: saved_mode,
+        })
+    }
+
+    /// Queue up any keys pressed since the last look.
+    fn read_keys(&mut self) {
+        let mut buffer = [0; 64];
+        match self.input.read(&mut buffer) {
+            Ok(read) => self.keys.extend(parse_keys(&buffer[..read])),
+            // The connection is gone
+            Err(_) => self.closed = true,
+        }
+    }
+
+    /// The escape sequences to bring the terminal up to date with the
+    /// frame.
+    fn changes(&self) -> String {
+        let mut out = String::new();
+        let mut colors = None;
+        // Where the terminal's cursor is, after the last glyph written
+        let mut cursor = None;
+        for y in 0..self.frame.height() {
+            for x in 0..self.frame.width() {
+                let cell = self.frame.cell(x, y);
+                if self.drawn.as_ref().map_or(false, |drawn| {
+                    drawn.cell(x, y) == cell
+                }) {
+                    continue;
+                }
+                if cursor != Some((x, y)) {
+                    let _ = write!(out, "\x1b[{};{}H", y + 1, x + 1);
+                }
+                if colors != Some((cell.color, cell.background)) {
+                    let (fg, bg) = (cell.color, cell.background);
+                    let _ = write!(out, "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                                   fg.r, fg.g, fg.b, bg.r, bg.g, bg.b);
+                    colors = Some((fg, bg));
+                }
+                out.push(printable(cell));
+                cursor = Some((x + 1, y));
+            }
+        }
+        out
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(LEAVE.as_bytes());
+        let _ = stdout.flush();
+        let _ = stty(&[&self.saved_mode]);
+    }
+}
+
+/// The character to send for a cell. Anything the terminal would treat
+/// as a control code is left blank.
+fn printable(cell: Cell) -> char {
+    if cell.glyph.is_control() {
+        ' '
+    } else {
+        cell.glyph
+    }
+}
+
+fn key(code: KeyCode, printable: char) -> Key {
+    Key {
+        code: code,
+        printable: printable,
+        pressed: true,
+        ..Default::default()
+    }
+}
+
+/// Add the modifiers from an xterm style parameter, e.g. the 5 in
+/// "ESC [ 1 ; 5 A" for ctrl and up.
+fn with_modifiers(mut key: Key, parameter: u8) -> Key {
+    let bits = parameter.saturating_sub(1);
+    key.shift = bits & 1 != 0;
+    key.alt = bits & 2 != 0;
+    key.left_alt = key.alt;
+    key.ctrl = bits & 4 != 0;
+    key.left_ctrl = key.ctrl;
+    key
+}
+
+/// The key a character typed on its own stands for.
+fn char_key(byte: u8) -> Key {
+    match byte {
+        b'\r' | b'\n' => key(KeyCode::Enter, '\r'),
+        b'\t' => key(KeyCode::Tab, '\t'),
+        0x08 | 0x7f => key(KeyCode::Backspace, '\0'),
+        0x1b => key(KeyCode::Escape, '\0'),
+        b' ' => key(KeyCode::Spacebar, ' '),
+        // Ctrl and a letter arrive as 1 to 26
+        0x01...0x1a => {
+            let mut ctrl_key = key(KeyCode::Char, (b'a' + byte - 1) as char);
+            ctrl_key.ctrl = true;
+            ctrl_key.left_ctrl = true;
+            ctrl_key
+        }
+        _ => {
+            let typed = byte as char;
+            let mut char_key = key(KeyCode::Char, typed);
+            char_key.shift = typed.is_ascii_uppercase();
+            char_key
+        }
+    }
+}
+
+/// The key named by the end of an escape sequence: the letter closing it
+/// and, for the "ESC [ n ~" kind, the number before the tilde.
+fn sequence_key(number: u8, last: u8) -> Option<Key> {
+    let code = match (last, number) {
+        (b'A', _) => KeyCode::Up,
+        (b'B', _) => KeyCode::Down,
+        (b'C', _) => KeyCode::Right,
+        (b'D', _) => KeyCode::Left,
+        (b'H', _) | (b'~', 1) | (b'~', 7) => KeyCode::Home,
+        (b'F', _) | (b'~', 4) | (b'~', 8) => KeyCode::End,
+        (b'E', _) => KeyCode::NumPad5,
+        (b'P', _) | (b'~', 11) => KeyCode::F1,
+        (b'Q', _) | (b'~', 12) => KeyCode::F2,
+        (b'R', _) | (b'~', 13) => KeyCode::F3,
+        (b'S', _) | (b'~', 14) => KeyCode::F4,
+        (b'~', 2) => KeyCode::Insert,
+        (b'~', 3) => KeyCode::Delete,
+        (b'~', 5) => KeyCode::PageUp,
+        (b'~', 6) => KeyCode::PageDown,
+        (b'~', 15) => KeyCode::F5,
+        (b'~', 17) => KeyCode::F6,
+        (b'~', 18) => KeyCode::F7,
+        (b'~', 19) => KeyCode::F8,
+        (b'~', 20) => KeyCode::F9,
+        (b'~', 21) => KeyCode::F10,
+        (b'~', 23) => KeyCode::F11,
+        (b'~', 24) => KeyCode::F12,
+        _ => return None,
+    };
+    Some(key(code, '\0'))
+}
+
+/// Turn the bytes read from the terminal into key presses, the same ones
+/// libtcod would report so the keymap works unchanged.
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let mut position = 0;
+    while position < bytes.len() {
+        let byte = bytes[position];
+        position += 1;
+        if byte != 0x1b || position == bytes.len() {
+            if byte < 0x80 {
+                keys.push(char_key(byte));
+            }
+            continue;
+        }
+
+        // "ESC [" and "ESC O" start the sequences for the keys that don't
+        // type anything. Escape before anything else is alt.
+        let introducer = bytes[position];
+        if introducer != b'[' && introducer != b'O' {
+            position += 1;
+            let mut alt_key = char_key(introducer);
+            alt_key.alt = true;
+            alt_key.left_alt = true;
+            keys.push(alt_key);
+            continue;
+        }
+        position += 1;
+        // Up to two numbers split by ';': the key and its modifiers
+        let mut numbers = vec![0u8];
+        while position < bytes.len() {
+            let byte = bytes[position];
+            position += 1;
+            match byte {
+                b'0'...b'9' => {
+                    let last = numbers.len() - 1;
+                    numbers[last] = numbers[last].saturating_mul(10)
+                        .saturating_add(byte - b'0');
+                }
+                b';' => numbers.push(0),
+                _ => {
+                    let modifiers = numbers.get(1).cloned().unwrap_or(1);
+                    if let Some(key) = sequence_key(numbers[0], byte) {
+                        keys.push(with_modifiers(key, modifiers));
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    keys
+}
+
+impl Surface for Terminal {
+    fn width(&self) -> i32 {
+        self.frame.width()
+    }
+
+    fn height(&self) -> i32 {
+        self.frame.height()
+    }
+
+    fn cell(&self, x: i32, y: i32) -> Cell {
+        self.frame.cell(x, y)
+    }
+
+    fn put_char(&mut self, x: i32, y: i32, glyph: char, color: Color) {
+        self.frame.put_char(x, y, glyph, color);
+    }
+
+    fn set_background(&mut self, x: i32, y: i32, color: Color) {
+        self.frame.set_background(x, y, color);
+    }
+}
+
+impl Screen for Terminal {
+    fn flush(&mut self) {
+        let changes = self.changes();
+        let mut stdout = io::stdout();
+        if stdout.write_all(changes.as_bytes())
+            .and_then(|_| stdout.flush()).is_err() {
+                self.closed = true;
+            }
+        self.drawn = Some(self.frame.clone());
+
+        let frame_time = Duration::from_millis(1000 /
+                                               consts::LIMIT_FPS as u64);
+        let elapsed = self.last_flush.elapsed();
+        if elapsed < frame_time {
+            thread::sleep(frame_time - elapsed);
+        }
+        self.last_flush = Instant::now();
+    }
+
+    fn check_for_event(&mut self) -> Option<Event> {
+        if self.keys.is_empty() {
+            self.read_keys();
+        }
+        self.keys.pop_front().map(Event::Key)
+    }
+
+    fn wait_for_keypress(&mut self) -> Key {
+        loop {
+            if let Some(Event::Key(key)) = self.check_for_event() {
+                return key;
+            }
+            if self.closed {
+                return Default::default();
+            }
+            thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+        }
+    }
+
+    fn window_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn draw_image(&mut self, _filename: &str) {
+        self.frame.clear(colors::BLACK);
+    }
+
+    fn apply_settings(&mut self, _settings: &Settings, _fonts: &[Font]) {
+        // The frame always fills the terminal, whatever size is set
+        self.drawn = None;
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(b"\x1b[0m\x1b[2J");
+        let _ = stdout.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tcod::input::{Key, KeyCode};
+
+    use super::{parse_keys, parse_size, sequence_key};
+
+    // The code, character, ctrl, alt and shift of each key
+    fn summary(keys: &[Key]) -> Vec<(KeyCode, char, bool, bool, bool)> {
+        keys.iter().map(|key| {
+            (key.code, key.printable, key.ctrl, key.alt, key.shift)
+        }).collect()
+    }
+
+    #[test]
+    fn plain_characters() {
+        assert_eq!(summary(&parse_keys(b"aB\r")),
+                   vec![(KeyCode::Char, 'a', false, false, false),
+                        (KeyCode::Char, 'B', false, false, true),
+                        (KeyCode::Enter, '\r', false, false, false)]);
+    }
+
+    #[test]
+    fn lone_escape() {
+        assert_eq!(summary(&parse_keys(b"\x1b")),
+                   vec![(KeyCode::Escape, '\0', false, false, false)]);
+    }
+
+    #[test]
+    fn escape_then_a_letter_is_alt() {
+        assert_eq!(summary(&parse_keys(b"\x1bx")),
+                   vec![(KeyCode::Char, 'x', false, true, false)]);
+    }
+
+    #[test]
+    fn ctrl_and_a_letter() {
+        assert_eq!(summary(&parse_keys(b"\x11")),
+                   vec![(KeyCode::Char, 'q', true, false, false)]);
+    }
+
+    #[test]
+    fn arrow_with_modifiers() {
+        assert_eq!(summary(&parse_keys(b"\x1b[1;5A")),
+                   vec![(KeyCode::Up, '\0', true, false, false)]);
+        assert_eq!(summary(&parse_keys(b"\x1b[1;2D\x1b[B")),
+                   vec![(KeyCode::Left, '\0', false, false, true),
+                        (KeyCode::Down, '\0', false, false, false)]);
+    }
+
+    #[test]
+    fn numbered_sequences() {
+        assert_eq!(summary(&parse_keys(b"\x1b[5~")),
+                   vec![(KeyCode::PageUp, '\0', false, false, false)]);
+        assert_eq!(summary(&parse_keys(b"\x1bOP")),
+                   vec![(KeyCode::F1, '\0', false, false, false)]);
+    }
+
+    #[test]
+    fn both_backspaces() {
+        assert_eq!(summary(&parse_keys(b"\x08\x7f")),
+                   vec![(KeyCode::Backspace, '\0', false, false, false),
+                        (KeyCode::Backspace, '\0', false, false, false)]);
+    }
+
+    #[test]
+    fn unknown_sequences_are_dropped() {
+        assert!(sequence_key(99, b'~').is_none());
+        assert_eq!(summary(&parse_keys(b"\x1b[99~a")),
+                   vec![(KeyCode::Char, 'a', false, false, false)]);
+    }
+
+    #[test]
+    fn size_is_rows_then_columns() {
+        assert_eq!(parse_size("40 120"), Some((120, 40)));
+        assert_eq!(parse_size(""), None);
+        assert_eq!(parse_size("40"), None);
+        assert_eq!(parse_size("40 wide"), None);
+    }
+}
@@ -1,87 +1,122 @@
-extern crate tcod;
-use tcod::console::{
-    BackgroundFlag,
-    blit,
-    Console,
-    FontLayout,
-    FontType,
-    Offscreen,
-    Root,
-    TextAlignment,
-};
-use tcod::input::Mouse;
-use tcod::map::{Map as FovMap, FovAlgorithm};
+use tcod::input::{KeyCode, Mouse};
+use tcod::map::Map as FovMap;
 use tcod::colors::{self, Color};
 
-use std::ascii::AsciiExt;
+use std::cmp;
+use std::error::Error;
 
+use ai;
+use background::Background;
 use consts;
+use crafting::{self, Recipe};
+use dialogue;
+use fire;
 use game;
 use game::Game;
-use log::MessageType;
+use keymap::{self, KeyAction, Keymap};
+use log::{self, MessageType};
 use map::{self, Map};
-use object::{self, Object};
+use object::{self, actor, Object};
+use object::character;
+use object::container::Transfer;
+use object::interact::Interaction;
+use replay;
+use settings::{self, Font, Layout, PanelPosition, Settings};
+use sight::{self, Sight};
+use travel;
+use util::rng;
+
+pub mod backend;
+pub mod theme;
+pub mod window;
+
+use ui::backend::{Canvas, Screen, Surface};
+use ui::theme::Theme;
+use ui::window::{Content, Outcome, Purpose, Window, Windows};
 
 pub struct Ui {
-    pub root: Root,
-    pub con: Offscreen,
-    pub panel: Offscreen,
-    pub fov: FovMap,
+    pub root: Box<Screen>,
+    pub con: Canvas,
+    pub panel: Canvas,
     pub mouse: Mouse,
+    pub sight: Sight,
+    // Screens open over the game, see `window`
+    pub windows: Windows,
+    pub fonts: Vec<Font>,
+    pub layout: Layout,
+    pub settings: Settings,
+    pub theme: Theme,
+    // Every palette on offer, the default one first
+    pub themes: Vec<Theme>,
 }
 
-const COLOR_ALERT: Color = colors::RED;
-const COLOR_INFO: Color = colors::LIGHTER_GREY;
-const COLOR_SUCCESS: Color = colors::GREEN;
-const COLOR_STATUS_CHANGE: Color = colors::WHITE;
-
-const COLOR_DARK_WALL: Color = colors::BLACK;
-const COLOR_LIGHT_WALL: Color = colors::DARKEST_GREY;
-const COLOR_DARK_GROUND: Color = colors::DARKER_GREY;
-const COLOR_LIGHT_GROUND: Color = colors::GREY;
-
-const FOV_ALGO: FovAlgorithm = FovAlgorithm::Shadow;
-const FOV_LIGHT_WALLS: bool = true;
-const TORCH_RADIUS: i32 = 3;
-
-
-pub fn initialize(title: &str) -> Ui {
-    let root = Root::initializer()
-    // .font("arial10x10.png", FontLayout::Ui)
-        .font("bluebox.png", FontLayout::AsciiInRow)
-        .font_type(FontType::Greyscale)
-        .size(consts::SCREEN_WIDTH, consts::SCREEN_HEIGHT)
-        .title(title)
-        .init();
-
-    tcod::system::set_fps(consts::LIMIT_FPS);
-
-    Ui {
+// How far remembered things fade into the background, from 0 to 1
+const REMEMBERED_DIMMING: f32 = 0.6;
+
+const MAX_NAME_LENGTH: usize = 20;
+// Windows full of text, like the help and message history. Fits the
+// smallest screen size.
+const WIDE_WINDOW_WIDTH: i32 = 58;
+
+
+/// Open the game window, or take over the terminal, as the player's
+/// settings say, falling back on the defaults if they can't be read.
+pub fn initialize(title: &str, kind: backend::Kind)
+                  -> Result<Ui, Box<Error>> {
+    let settings = settings::load_settings(settings::SETTINGS_FILE)
+        .unwrap_or_else(|_| Settings::new());
+    // The game can still start in its own font and colors if these files
+    // are missing
+    let fonts = settings::load_fonts(settings::FONTS_FILE)
+        .unwrap_or_else(|_| vec![Font::new()]);
+    let themes = theme::load_themes(theme::THEMES_FILE)
+        .unwrap_or_else(|_| vec![Theme::new()]);
+    let theme = find_theme(&themes, &settings.theme);
+    let root = try!{ backend::open(kind, title, &settings, &fonts) };
+    let layout = Layout::sized(&settings, root.width(), root.height());
+
+    Ok(Ui {
         root: root,
-        con: Offscreen::new(map::MAP_WIDTH, map::MAP_HEIGHT),
-        panel: Offscreen::new(consts::SCREEN_WIDTH, consts::PANEL_HEIGHT),
-        // fov: FovMap::new(map::MAP_WIDTH, map::MAP_HEIGHT),
-        fov: FovMap::new(map::FLOOR_WIDTH, map::FLOOR_HEIGHT),
+        con: Canvas::new(layout.map_width, layout.map_height),
+        panel: Canvas::new(layout.screen_width, layout.panel_height),
         mouse: Default::default(),
-    }
+        sight: Sight::new(),
+        windows: Windows::new(),
+        fonts: fonts,
+        layout: layout,
+        settings: settings,
+        theme: theme,
+        themes: themes,
+    })
+}
+
+/// The palette called `name`, or the default one.
+fn find_theme(themes: &[Theme], name: &Option<String>) -> Theme {
+    name.as_ref()
+        .and_then(|name| themes.iter().find(|theme| &theme.name == name))
+        .unwrap_or(&themes[0])
+        .clone()
+}
 
+/// Reopen the window and lay the screen out again after the settings
+/// have changed.
+fn apply_settings(game_ui: &mut Ui) {
+    game_ui.root.apply_settings(&game_ui.settings, &game_ui.fonts);
+    game_ui.layout = Layout::sized(&game_ui.settings, game_ui.root.width(),
+                                   game_ui.root.height());
+    game_ui.con = Canvas::new(game_ui.layout.map_width,
+                              game_ui.layout.map_height);
+    game_ui.panel = Canvas::new(game_ui.layout.screen_width,
+                                game_ui.layout.panel_height);
+    game_ui.theme = find_theme(&game_ui.themes, &game_ui.settings.theme);
 }
 
 pub fn initialize_fov(map: &Map, actors: &[Object], game_ui: &mut Ui) {
-    for y in 0..map::FLOOR_HEIGHT {
-        for x in 0..map::FLOOR_WIDTH {
-            game_ui.fov.set(x, y,
-                         map::blocks_view(x, y, map, actors) !=
-                            object::Blocks::Full,
-                            map::is_blocked(x, y,  map, actors) ==
-                            object::Blocks::No
-            );
-        }
-    }
-    game_ui.con.clear();
+    sight::refresh(map, actors, &mut game_ui.sight);
+    game_ui.con.clear(colors::BLACK);
 }
 
-fn render_bar(panel: &mut Offscreen,
+fn render_bar(panel: &mut Canvas,
               x: i32,
               y: i32,
               total_width: i32,
@@ -91,68 +126,100 @@ fn render_bar(panel: &mut Offscreen,
               bar_color: Color,
               back_color: Color) {
     let bar_width = (value as f32 / maximum as f32 * total_width as f32) as i32;
-    panel.set_default_background(back_color);
-    panel.rect(x, y, total_width, 1, false, BackgroundFlag::Screen);
+    panel.fill_background(x, y, total_width, 1, back_color);
 
-    panel.set_default_background(bar_color);
     if bar_width > 0 {
-        panel.rect(x, y, bar_width, 1, false, BackgroundFlag::Screen);
+        panel.fill_background(x, y, bar_width, 1, bar_color);
     }
 
-    panel.set_default_foreground(colors::WHITE);
-    panel.print_ex(x + total_width / 2, y, BackgroundFlag::None,
-                   TextAlignment::Center, &format!("{}: {}/{}",
-                                                   name, value, maximum));
+    panel.print_centered(x + total_width / 2, y,
+                         &format!("{}: {}/{}", name, value, maximum),
+                         colors::WHITE);
 }
 
-fn get_message_color(message_type: &MessageType) -> Color {
+fn get_message_color(theme: &Theme, message_type: &MessageType) -> Color {
     match message_type {
-        &MessageType::Alert => COLOR_ALERT,
-        &MessageType::Info => COLOR_INFO,
-        &MessageType::StatusChange => COLOR_STATUS_CHANGE,
-        &MessageType::Success => COLOR_SUCCESS,
+        &MessageType::Alert => theme.alert,
+        &MessageType::Info => theme.info,
+        &MessageType::StatusChange => theme.status_change,
+        &MessageType::Success => theme.success,
     }
 }
 
 
+/// The background of the map tile at (x, y), as the floor is drawn.
+fn floor_color(x: i32, y: i32, game: &Game, actors: &[Object], sight: &Sight,
+               theme: &Theme) -> Color {
+    let wall = map::blocks_view(x, y, &game.map, actors);
+    let visible = sight::is_visible(x, y, sight, &game.map);
+    let game_tile = &game.map[x as usize][y as usize];
+    let color = match (visible, wall) {
+        (false, object::Blocks::Full) => theme.dark_wall,
+        (false, object::Blocks::No) |
+        (false, object::Blocks::Half) => theme.dark_ground,
+        (true, object::Blocks::Full) => theme.light_wall,
+        (true, object::Blocks::No) |
+        (true, object::Blocks::Half) => theme.light_ground,
+    };
+    if visible && game_tile.fire > 0 {
+        theme.fire_background
+    } else if visible && game_tile.smoke > 0 {
+        theme.smoke
+    } else {
+        color
+    }
+}
+
+/// Erase the actors from the map before they move. Those drawn on their
+/// own background get the floor's put back under them, as the floor is
+/// only redrawn when the player's view changes.
+pub fn clear_actors(game_ui: &mut Ui, game: &Game, actors: &[Object]) {
+    for object in actors {
+        object.clear(&mut game_ui.con);
+        let (x, y) = object.pos();
+        if object.background.is_some() &&
+            game.map[x as usize][y as usize].explored {
+                let color = floor_color(x, y, game, actors, &game_ui.sight,
+                                        &game_ui.theme);
+                game_ui.con.set_background(x, y, color);
+            }
+    }
+}
+
 pub fn render_all(game_ui: &mut Ui, game: &mut Game, actors: &[Object],
               fov_recompute: bool) {
     if fov_recompute {
-        let player = &actors[consts::PLAYER];
-        game_ui.fov.compute_fov(player.x, player.y, TORCH_RADIUS,
-                             FOV_LIGHT_WALLS, FOV_ALGO);
-
         for x in 0..map::FLOOR_WIDTH {
             for y in 0..map::FLOOR_HEIGHT {
-                let wall = map::blocks_view(x, y, & game.map, actors);
+                let visible = sight::is_visible(x, y, &game_ui.sight,
+                                                &game.map);
+                let color = floor_color(x, y, game, actors, &game_ui.sight,
+                                        &game_ui.theme);
                 let game_tile = &mut game.map[x as usize][y as usize];
-                let visible = game_ui.fov.is_in_fov(x, y);
-                // let visible = true;
-
-                // let wall = game.map[x as usize][y as usize].blocks_view();
-                let color = match(visible, wall) {
-                    (false, object::Blocks::Full) => COLOR_DARK_WALL,
-                    (false, object::Blocks::No) |
-                    (false, object::Blocks::Half)
-                    => COLOR_DARK_GROUND,
-                    (true, object::Blocks::Full) => COLOR_LIGHT_WALL,
-                    (true, object::Blocks::No) |
-                    (true, object::Blocks::Half) => COLOR_LIGHT_GROUND,
-                    // (_, _) => COLOR_LIGHT_GROUND,
-                };
-                let explored =
-                    &mut game_tile.explored;
                 if visible {
+                    game_ui.con.put_char(x, y, ' ', colors::WHITE);
                     for item in &game_tile.items {
-                        item.draw(&mut game_ui.con);
+                        item.draw(&mut game_ui.con, &game_ui.theme);
+                    }
+                    if game_tile.fire > 0 {
+                        game_ui.con.put_char(x, y, '^', game_ui.theme.fire);
+                    }
+                } else if game_tile.explored {
+                    // Out of view, draw what was there last time, dimmed
+                    game_ui.con.put_char(x, y, ' ', colors::WHITE);
+                    let topmost = game_tile.seen_monster.as_ref()
+                        .or(game_tile.seen_items.last());
+                    if let Some(remembered) = topmost {
+                        let seen_color =
+                            game_ui.theme.object_color(remembered.color);
+                        game_ui.con.put_char(x, y, remembered.symbol,
+                                             colors::lerp(seen_color, color,
+                                                          REMEMBERED_DIMMING));
                     }
-
-                    *explored = true;
                 }
 
-                if *explored {
-                    game_ui.con.set_char_background(x, y, color,
-                                                    BackgroundFlag::Set);
+                if game_tile.explored {
+                    game_ui.con.set_background(x, y, color);
                 }
             }
         }
@@ -160,168 +227,776 @@ pub fn render_all(game_ui: &mut Ui, game: &mut Game, actors: &[Object],
 
 
     let mut to_draw: Vec<_> = actors.iter()
-        .filter(|o| game_ui.fov.is_in_fov(o.x, o.y)).collect();
+        .filter(|o| sight::is_visible(o.x, o.y, &game_ui.sight, &game.map))
+        .collect();
 
     to_draw.sort_by(|o1, o2| { o1.blocks.cmp(&o2.blocks) });
     for object in &to_draw {
-        object.draw(&mut game_ui.con);
+        object.draw(&mut game_ui.con, &game_ui.theme);
     }
 
-    blit(&mut game_ui.con, (0, 0), (map::MAP_WIDTH, map::MAP_HEIGHT),
-         &mut game_ui.root, (0, 0), 1.0, 1.0);
+    let layout = game_ui.layout;
+    backend::blit(&game_ui.con, &mut *game_ui.root, layout.map_x,
+                  layout.map_y, 1.0);
 
-    game_ui.panel.set_default_background(colors::BLACK);
-    game_ui.panel.clear();
+    game_ui.panel.clear(colors::BLACK);
 
     // print the game messages, one line at a time
-    let mut y = consts::MSG_HEIGHT as i32;
+    let mut y = layout.msg_height;
     for &(ref msg, ref message_type) in game.log.iter().rev() {
-        let msg_height = game_ui.panel.get_height_rect(consts::MSG_X, y,
-                                               consts::MSG_WIDTH, 0, msg);
-        y -= msg_height;
+        let msg_height = window::wrap(msg, layout.msg_width as usize).len();
+        y -= msg_height as i32;
         if y < 0 {
             break;
         }
-        game_ui.panel.set_default_foreground(get_message_color(message_type));
-        game_ui.panel.print_rect(consts::MSG_X, y, consts::MSG_WIDTH, 0, msg);
+        game_ui.panel.print_wrapped(layout.msg_x, y, layout.msg_width, msg,
+                                    get_message_color(&game_ui.theme,
+                                                      message_type));
     }
 
     // show the player's stats
     let hp = actors[consts::PLAYER].fighter.map_or(0, |f| f.hp);
     let max_hp = actors[consts::PLAYER].fighter.map_or(0, |f| f.max_hp);
-    render_bar(&mut game_ui.panel, 1, 1, consts::BAR_WIDTH, "HP", hp, max_hp,
-               colors::LIGHT_RED, colors::DARKER_RED);
+    render_bar(&mut game_ui.panel, 1, 1, layout.bar_width, "HP", hp, max_hp,
+               game_ui.theme.hp_bar, game_ui.theme.hp_bar_back);
+
+    if let Some(stamina) = actors[consts::PLAYER].stamina {
+        render_bar(&mut game_ui.panel, 1, 3, layout.bar_width, "Stamina",
+                   stamina.stamina, stamina.max_stamina,
+                   game_ui.theme.stamina_bar, game_ui.theme.stamina_bar_back);
+    }
+    game_ui.panel.print(1, 4, actors[consts::PLAYER].move_mode.name(),
+                        colors::LIGHT_GREY);
+    let effects: Vec<_> = actors[consts::PLAYER].status.iter()
+        .map(|effect| effect.kind.name()).collect();
+    game_ui.panel.print(1, 5, &effects.join(", "), game_ui.theme.alert);
+
+    if let Some(ref inventory) = actors[consts::PLAYER].inventory {
+        let load = actor::carried_weight(inventory);
+        let capacity = actor::carry_capacity(&actors[consts::PLAYER]);
+        let color = if load > capacity {
+            game_ui.theme.alert
+        } else {
+            colors::LIGHT_GREY
+        };
+        game_ui.panel.print(1, 2, &format!("Load: {:.1}/{:.1} kg", load,
+                                           capacity), color);
+    }
 
-    game_ui.panel.set_default_foreground(colors::LIGHT_GREY);
-    game_ui.panel.print_ex(1, 0, BackgroundFlag::None, TextAlignment::Left,
-                   get_names_under_mouse(game_ui.mouse, actors, &game_ui.fov));
+    let names = get_names_under_mouse(game_ui.mouse, &layout, game, actors,
+                                      &game_ui.sight);
+    game_ui.panel.print(1, 0, &names, colors::LIGHT_GREY);
     // blit the contents of `panel` to the root console
-    blit(&mut game_ui.panel, (0, 0), (consts::SCREEN_WIDTH, consts::PANEL_HEIGHT),
-         &mut game_ui.root, (0, consts::PANEL_Y), 1.0, 1.0);
+    backend::blit(&game_ui.panel, &mut *game_ui.root, 0, layout.panel_y,
+                  1.0);
 }
 
 fn menu<T: AsRef<str>>(header: &str, options: &[T], width: i32,
-                       root: &mut Root) -> Option<usize> {
-    let header_height = if header.is_empty() {
-        0
+                       game_ui: &mut Ui) -> Option<usize> {
+    match window::run_modal(Window::list(header, options, width,
+                                         Purpose::Info),
+                            &mut *game_ui.root, &game_ui.theme) {
+        Outcome::Chosen(choice) => Some(choice),
+        _ => None,
+    }
+}
+
+/// The player's inventory, to pick an item from for `purpose`.
+pub fn inventory_window(inventory: &[Object], header: &str, purpose: Purpose)
+                        -> Window {
+    if inventory.is_empty() {
+        return Window::text("", "Inventory is empty.", consts::INVENTORY_WIDTH);
+    }
+    let options: Vec<_> = inventory.iter().map(|item| {
+        match item.equipment {
+            Some(ref equipment) if equipment.equipped =>
+                format!("{} (on {})", item.display_name(), equipment.slot),
+            _ => item.display_name(),
+        }
+    }).collect();
+    Window::list(header, &options, consts::INVENTORY_WIDTH, purpose)
+}
+
+pub enum PickUpChoice {
+    Everything,
+    // Index into the tile's item list
+    Item(usize),
+}
+
+/// Let the player choose what to pick up from the items on a tile.
+pub fn pick_up_menu(tile_items: &[Object], game_ui: &mut Ui)
+                    -> Option<PickUpChoice> {
+    let item_ids: Vec<usize> = (0..tile_items.len())
+        .filter(|&id| tile_items[id].can_pick_up).collect();
+    let mut options = vec!["Everything".to_string()];
+    options.extend(item_ids.iter().map(|&id| tile_items[id].display_name()));
+
+    match menu("Pick up what?\n", &options, consts::INVENTORY_WIDTH, game_ui) {
+        Some(0) => Some(PickUpChoice::Everything),
+        Some(choice) => Some(PickUpChoice::Item(item_ids[choice - 1])),
+        None => None,
+    }
+}
+
+/// List every known recipe, marking the ones that can be made right now
+/// from `inventory`.
+pub fn crafting_menu(recipes: &[Recipe], inventory: &[Object],
+                     game_ui: &mut Ui) -> Option<usize> {
+    let options: Vec<String> = recipes.iter().map(|recipe| {
+        if crafting::can_craft(recipe, inventory) {
+            format!("{} (ready)", recipe.name)
+        } else {
+            format!("{} (need {})", recipe.name,
+                    crafting::missing(recipe, inventory).join(", "))
+        }
+    }).collect();
+    menu("Make what?\n", &options, consts::INVENTORY_WIDTH, game_ui)
+}
+
+pub fn container_menu(names: &[String], game_ui: &mut Ui) -> Option<usize> {
+    menu("Search what?\n", names, consts::INVENTORY_WIDTH, game_ui)
+}
+
+/// Pick one of the places the player remembers to travel back to.
+pub fn travel_menu(features: &[travel::Feature], player: &Object,
+                   game_ui: &mut Ui) -> Option<usize> {
+    let options: Vec<_> = features.iter().map(|feature| {
+        format!("{} ({} steps away)", feature.name,
+                cmp::max((feature.x - player.x).abs(),
+                         (feature.y - player.y).abs()))
+    }).collect();
+    let header = if features.is_empty() {
+        "You haven't found anywhere worth going back to.\n"
     } else {
-        root.get_height_rect(0, 0, width, consts::SCREEN_HEIGHT, header)
+        "Travel to where?\n"
     };
+    menu(header, &options, consts::INVENTORY_WIDTH, game_ui)
+}
 
-    let options_len = options.len() as i32;
-    assert!(options_len <= consts::MAX_INVENTORY_ITEMS,
-            format!("Cannot have a menu with more than {} options.",
-            consts::MAX_INVENTORY_ITEMS));
+/// Ask what to do with `name` when bumping into it.
+pub fn interaction_menu(name: &str, interactions: &[Interaction],
+                        game_ui: &mut Ui) -> Option<usize> {
+    let options: Vec<String> = interactions.iter()
+        .map(|interaction| format!("{} the {}", interaction.verb(), name))
+        .collect();
+    menu("What do you do?\n", &options, consts::INVENTORY_WIDTH, game_ui)
+}
 
-    // let header_height = root.get_height_rect(0, 0,
-    //                                          width, consts::SCREEN_HEIGHT, header);
-    let height = options_len + header_height;
+pub fn talk_menu(names: &[String], game_ui: &mut Ui) -> Option<usize> {
+    menu("Talk to whom?\n", names, consts::INVENTORY_WIDTH, game_ui)
+}
 
-    let mut window = Offscreen::new(width, height);
+/// Show what `speaker` says at `node` and let the player pick a reply.
+pub fn dialogue_menu(speaker: &str, node: &dialogue::Node, game_ui: &mut Ui)
+                     -> Option<usize> {
+    let options: Vec<&str> = node.choices.iter()
+        .map(|choice| choice.text.as_str()).collect();
+    menu(&format!("{}: \"{}\"\n", speaker, node.text), &options,
+         consts::INVENTORY_WIDTH, game_ui)
+}
+
+/// Pick an item to barter with, listing what each is worth. When
+/// `offered` is given it holds how many of each stack are already on the
+/// table.
+pub fn barter_menu(header: &str, items: &[Object], prices: &[i32],
+                   offered: Option<&[i32]>, game_ui: &mut Ui)
+                   -> Option<usize> {
+    let options: Vec<String> = items.iter().enumerate().map(|(id, item)| {
+        match offered {
+            Some(offered) if offered[id] > 0 =>
+                format!("{} - {} (offering {})", item.display_name(),
+                        prices[id], offered[id]),
+            _ => format!("{} - {}", item.display_name(), prices[id]),
+        }
+    }).collect();
+    menu(header, &options, consts::INVENTORY_WIDTH, game_ui)
+}
 
-    window.set_default_foreground(colors::WHITE);
-    window.print_rect_ex(0, 0, width, height, BackgroundFlag::None,
-                         TextAlignment::Left, header);
+/// Pick one item to move between a container and the player's inventory.
+pub fn transfer_menu(name: &str, contents: &[Object], inventory: &[Object],
+                     game_ui: &mut Ui) -> Option<Transfer> {
+    let mut options: Vec<String> = contents.iter()
+        .map(|item| format!("Take {}", item.display_name())).collect();
+    options.extend(inventory.iter()
+                   .map(|item| format!("Put {}", item.display_name())));
+    let header = if contents.is_empty() {
+        format!("The {} is empty.\n", name)
+    } else {
+        format!("Inside the {}:\n", name)
+    };
 
-    for (index, option_text) in options.iter().enumerate() {
-        let menu_letter = (b'a' + index as u8) as char;
-        let text = format!("({}) {}", menu_letter, option_text.as_ref());
-        window.print_ex(0, header_height + index as i32,
-                        BackgroundFlag::None, TextAlignment::Left, text);
+    menu(&header, &options, consts::INVENTORY_WIDTH, game_ui).map(|choice| {
+        if choice < contents.len() {
+            Transfer::Take(choice)
+        } else {
+            Transfer::Put(choice - contents.len())
+        }
+    })
+}
+
+/// Everything the player can tell about an object by looking it over.
+fn describe(object: &Object) -> String {
+    let mut lines = vec![object.display_name(), "".into()];
+    if !object.description.is_empty() {
+        lines.push(object.description.clone());
+        lines.push("".into());
+    }
+    if let Some(fighter) = object.fighter {
+        lines.push(format!("Health: {}/{}", fighter.hp, fighter.max_hp));
+        lines.push(format!("Power: {}  Defense: {}", object.power(),
+                           object.defense()));
+    }
+    if !object.status.is_empty() {
+        let effects: Vec<_> = object.status.iter()
+            .map(|effect| effect.kind.name()).collect();
+        lines.push(format!("Currently: {}", effects.join(", ")));
+    }
+    if let Some(ref equipment) = object.equipment {
+        lines.push(format!("Worn on: {}{}", equipment.slot,
+                           if equipment.equipped { " (equipped)" } else { "" }));
+        if equipment.power_bonus != 0 {
+            lines.push(format!("Power: {:+}", equipment.power_bonus));
+        }
+        if equipment.defense_bonus != 0 {
+            lines.push(format!("Defense: {:+}", equipment.defense_bonus));
+        }
+        if let Some(durability) = equipment.durability {
+            lines.push(format!("Hits before breaking: {}", durability));
+        }
     }
+    if let Some(ref ranged) = object.ranged {
+        lines.push(format!("Shoots: {} for {} damage, up to {} tiles",
+                           ranged.ammo, ranged.damage, ranged.range));
+        lines.push(format!("Noise: {}", ranged.noise));
+    }
+    if let Some(ref container) = object.container {
+        lines.push(if container.searched {
+            "You have searched through it.".to_string()
+        } else {
+            "You haven't searched through it yet.".to_string()
+        });
+    }
+    if let Some(function) = object.function {
+        lines.push(format!("Effect: {}", function.description()));
+    }
+    if object.can_pick_up {
+        if object.count > 1 {
+            lines.push(format!("Weight: {:.2} kg ({:.2} kg each)",
+                               object.total_weight(), object.weight));
+        } else {
+            lines.push(format!("Weight: {:.2} kg", object.weight));
+        }
+    }
+    lines.join("\n")
+}
 
-    let x = consts::SCREEN_WIDTH / 2 - width / 2;
-    let y = consts::SCREEN_HEIGHT / 2 - height / 2;
-    blit(&mut window, (0, 0), (width, height), root, (x, y),
-         1.0, 0.7);
+pub fn examine(object: &Object, game_ui: &mut Ui) {
+    msgbox(&describe(object), consts::INVENTORY_WIDTH, game_ui);
+}
 
-    root.flush();
-    let key = root.wait_for_keypress(true);
+pub fn examine_window(object: &Object) -> Window {
+    Window::text("", &describe(object), consts::INVENTORY_WIDTH)
+}
 
-    if key.printable.is_alphabetic() {
-        let index = key.printable.to_ascii_lowercase() as usize - 'a' as usize;
-        if index < options.len() {
-            Some(index)
+/// The player's attributes on one tab and how far along each skill is on
+/// another.
+pub fn character_window(player: &Object) -> Window {
+    let character = match player.character {
+        Some(ref character) => character,
+        None => return Window::text("", &player.name, consts::INVENTORY_WIDTH),
+    };
+    let attributes = &character.attributes;
+    let about = format!("{}\n\nStrength: {}\nAgility: {}\nEndurance: {}",
+                        player.description, attributes.strength,
+                        attributes.agility, attributes.endurance);
+    let mut skills = String::new();
+    for &skill in character::SKILLS.iter() {
+        let training = character.training(skill);
+        if training.level >= character::MAX_SKILL_LEVEL {
+            skills.push_str(&format!("{}: {} (mastered)\n", skill.name(),
+                                     training.level));
         } else {
-            None
+            skills.push_str(&format!("{}: {} ({}/{} xp)\n", skill.name(),
+                                     training.level, training.xp,
+                                     training.xp_to_next()));
         }
+    }
+    let width = consts::INVENTORY_WIDTH;
+    let tabs = vec![
+        ("Attributes".to_string(), window::text_content(&about, width)),
+        ("Skills".to_string(), window::text_content(skills.trim_right(),
+                                                      width)),
+    ];
+    Window::new(&player.name, Content::Tabs { tabs: tabs, shown: 0 }, width,
+                Purpose::Info)
+}
+
+/// List every action with the keys bound to it in the current keymap.
+pub fn help_window(keymap: &Keymap) -> Window {
+    let text: Vec<_> = keymap.bindings().iter().map(|binding| {
+        format!("{}: {}", binding.action.description(),
+                binding.keys.join(", "))
+    }).collect();
+    Window::text("Keys\n", &text.join("\n"), WIDE_WINDOW_WIDTH)
+}
+
+/// Every message so far, scrolled to the latest.
+pub fn log_window(log: &log::Messages, theme: &Theme) -> Window {
+    let width = WIDE_WINDOW_WIDTH;
+    let mut lines = vec![];
+    for &(ref message, ref message_type) in log {
+        let color = get_message_color(theme, message_type);
+        lines.extend(window::wrap(message, width as usize).into_iter()
+                     .map(|line| (line, Some(color))));
+    }
+    let scroll = lines.len().saturating_sub(consts::MENU_PAGE_SIZE as usize);
+    Window::new("Messages\n", Content::Text { lines: lines, scroll: scroll },
+                width, Purpose::Info)
+}
+
+/// The glyph and color marking an explored tile on the overview map,
+/// picking whatever matters most about it.
+fn overview_glyph(tile: &map::Tile, theme: &Theme) -> (char, Color) {
+    let has = |object_type: &str| {
+        tile.seen_items.iter().find(|item| item.object_type == object_type)
+    };
+    if let Some(ref monster) = tile.seen_monster {
+        (monster.symbol, theme.overview_monster)
+    } else if has("stairs").is_some() {
+        ('>', theme.overview_stairs)
+    } else if let Some(container) = has("container") {
+        ('=', if container.searched == Some(true) {
+            theme.overview_looted
+        } else {
+            theme.overview_container
+        })
+    } else if has("door").is_some() {
+        ('+', theme.overview_door)
+    } else if has("wall").is_some() {
+        ('#', theme.overview_wall)
     } else {
-        None
+        ('.', theme.overview_floor)
     }
 }
 
-pub fn inventory_menu(inventory: &[Object], header: &str, root: &mut Root)
-                  -> Option<usize> {
-    let options = if inventory.len() == 0 {
-        vec!["Inventory is empty.".into()]
+/// A map of every floor the player has explored, one at a time. Left and
+/// right flip between floors, any other key closes it.
+pub fn overview(game: &Game, actors: &[Object], layout: &Layout,
+                theme: &Theme, root: &mut Screen) {
+    let mut floors: Vec<&Map> = game.past_floors.iter().collect();
+    floors.push(&game.map);
+    let mut shown = floors.len() - 1;
+    let (map_x, map_y) = (2, 3);
+    let legend_x = map_x + map::FLOOR_WIDTH + 4;
+    let legend = [("@", colors::WHITE, "You"),
+                  (">", theme.overview_stairs, "Stairs"),
+                  ("+", theme.overview_door, "Door"),
+                  ("=", theme.overview_container, "Not searched yet"),
+                  ("=", theme.overview_looted, "Searched"),
+                  ("Z", theme.overview_monster, "Monster, where last seen")];
+
+    loop {
+        let mut window = Canvas::new(layout.screen_width,
+                                     layout.screen_height);
+        window.print(map_x, 1, &format!("Floor {} of {} explored", shown + 1,
+                                        floors.len()), colors::WHITE);
+        let floor = floors[shown];
+        for x in 0..map::FLOOR_WIDTH {
+            for y in 0..map::FLOOR_HEIGHT {
+                let tile = &floor[x as usize][y as usize];
+                if !tile.explored {
+                    continue;
+                }
+                let (glyph, color) = overview_glyph(tile, theme);
+                window.put_char(map_x + x, map_y + y, glyph, color);
+            }
+        }
+        if shown == floors.len() - 1 {
+            let (x, y) = actors[consts::PLAYER].pos();
+            window.put_char(map_x + x, map_y + y, '@', colors::WHITE);
+        }
+
+        for (line, &(glyph, color, meaning)) in legend.iter().enumerate() {
+            let y = map_y + line as i32;
+            window.print(legend_x, y, glyph, color);
+            window.print(legend_x + 2, y, meaning, colors::LIGHT_GREY);
+        }
+        window.print(legend_x, map_y + legend.len() as i32 + 1,
+                     "Left and right switch floors.", colors::LIGHT_GREY);
+        backend::blit(&window, root, 0, 0, 1.0);
+        root.flush();
+
+        let key = root.wait_for_keypress();
+        match key.code {
+            KeyCode::Left if shown > 0 => shown -= 1,
+            KeyCode::Right if shown + 1 < floors.len() => shown += 1,
+            KeyCode::Left | KeyCode::Right => {}
+            _ => return,
+        }
+    }
+}
+
+/// Examine something on the map tile at (x, y), asking which object
+/// if there is more than one.
+pub fn examine_tile(x: i32, y: i32, game: &Game, actors: &[Object],
+                    game_ui: &mut Ui) {
+    let tile = &game.map[x as usize][y as usize];
+    if !sight::is_visible(x, y, &game_ui.sight, &game.map) {
+        return examine_remembered(tile, game_ui);
+    }
+    let mut objects: Vec<&Object> = actors.iter()
+        .filter(|obj| obj.pos() == (x, y))
+        .collect();
+    objects.extend(tile.items.iter());
+    objects.push(&tile.floor);
+
+    let chosen = if objects.len() == 1 {
+        Some(0)
     } else {
-        inventory.iter().map(|item| {item.name.clone() }).collect()
+        let names: Vec<_> = objects.iter().map(|obj| obj.display_name())
+            .collect();
+        menu("Examine what?\n", &names, consts::INVENTORY_WIDTH,
+             game_ui)
     };
+    if let Some(id) = chosen {
+        examine(objects[id], game_ui);
+    }
+}
 
-    let inventory_index = menu(header, &options,
-                               consts::INVENTORY_WIDTH, root);
+/// Examine a tile out of sight, which only shows what the player saw there
+/// the last time they looked.
+fn examine_remembered(tile: &map::Tile, game_ui: &mut Ui) {
+    let mut texts: Vec<(String, String)> = tile.seen_monster.iter()
+        .chain(tile.seen_items.iter())
+        .map(|seen| (seen.name.clone(), describe_remembered(seen)))
+        .collect();
+    texts.push((tile.floor.display_name(), describe(&tile.floor)));
+
+    let chosen = if texts.len() == 1 {
+        Some(0)
+    } else {
+        let names: Vec<_> = texts.iter().map(|&(ref name, _)| name.clone())
+            .collect();
+        menu("Examine what?\n", &names, consts::INVENTORY_WIDTH,
+             game_ui)
+    };
+    if let Some(id) = chosen {
+        msgbox(&texts[id].1, consts::INVENTORY_WIDTH, game_ui);
+    }
+}
 
+fn describe_remembered(seen: &map::Remembered) -> String {
+    let mut lines = vec![seen.name.clone(), "".into()];
+    if !seen.description.is_empty() {
+        lines.push(seen.description.clone());
+        lines.push("".into());
+    }
+    if seen.searched == Some(true) {
+        lines.push("You have searched it already.".into());
+    }
+    lines.push("That is how it looked when you last saw it.".into());
+    lines.join("\n")
+}
 
-    if inventory.len() > 0 {
-        inventory_index
-    } else {
-        None
+fn get_names_under_mouse(mouse: Mouse, layout: &Layout, game: &Game,
+                         actors: &[Object], sight: &Sight) -> String {
+    match layout.map_tile(mouse.cx as i32, mouse.cy as i32) {
+        Some((x, y)) if game.map[x as usize][y as usize].explored =>
+            look_at(x, y, game, actors, sight).join(", "),
+        _ => String::new(),
     }
 }
 
-fn get_names_under_mouse(mouse: Mouse, actors: &[Object], fov_map: &FovMap)
-    -> String {
-    let (x, y) = (mouse.cx as i32, mouse.cy as i32);
+/// Words for how hurt a fighter looks.
+fn apparent_health(fighter: &actor::Fighter) -> &'static str {
+    match 100 * fighter.hp / cmp::max(1, fighter.max_hp) {
+        percent if percent >= 100 => "unhurt",
+        percent if percent >= 75 => "lightly wounded",
+        percent if percent >= 50 => "wounded",
+        percent if percent >= 25 => "badly wounded",
+        _ => "nearly dead",
+    }
+}
 
-    let names = actors.iter().filter(
-        |obj| {obj.pos() == (x, y) && fov_map.is_in_fov(obj.x, obj.y)})
-        .map(|obj| obj.name.clone())
-        .collect::<Vec<_>>();
+/// Name a creature along with how hurt and how watchful it looks.
+fn describe_actor(id: usize, game: &Game, actors: &[Object],
+                  fov_map: &FovMap) -> String {
+    let actor = &actors[id];
+    match actor.fighter {
+        Some(ref fighter) if actor.alive => {
+            let mut traits = vec![apparent_health(fighter)];
+            let alertness = ai::alertness(id, game, actors, fov_map);
+            if !alertness.is_empty() {
+                traits.push(alertness);
+            }
+            format!("{} ({})", actor.display_name(), traits.join(", "))
+        }
+        _ => actor.display_name(),
+    }
+}
 
-    names.join(", ")
+/// One line for each thing the player can make out at (x, y): whoever is
+/// standing there, the objects on the tile and the floor itself. Out of
+/// sight the tile is described as it was last seen.
+fn look_at(x: i32, y: i32, game: &Game, actors: &[Object], sight: &Sight)
+           -> Vec<String> {
+    let tile = &game.map[x as usize][y as usize];
+    let mut lines = vec![];
+    if sight::is_visible(x, y, sight, &game.map) {
+        for (id, actor) in actors.iter().enumerate() {
+            if actor.pos() != (x, y) {
+                continue;
+            }
+            lines.push(if id == consts::PLAYER {
+                format!("You ({})", actor.fighter.as_ref()
+                        .map_or("dead", apparent_health))
+            } else {
+                describe_actor(id, game, actors, &sight.fov)
+            });
+        }
+        if tile.fire > 0 {
+            lines.push("Fire".to_string());
+        }
+        if tile.smoke > 0 {
+            lines.push("Smoke".to_string());
+        }
+    } else if let Some(ref monster) = tile.seen_monster {
+        lines.push(format!("{} (last seen here)", monster.name));
+    }
+    for item in &tile.seen_items {
+        lines.push(if item.searched == Some(true) {
+            format!("{} (searched)", item.name)
+        } else {
+            item.name.clone()
+        });
+    }
+    lines.push(tile.floor.display_name());
+    lines
+}
+
+/// Move a cursor over the map with the movement keys, describing whatever
+/// is under it. Enter examines something there more closely and escape
+/// goes back to the game.
+pub fn look(keymap: &Keymap, game: &mut Game, actors: &[Object],
+            game_ui: &mut Ui) {
+    let (mut x, mut y) = actors[consts::PLAYER].pos();
+    let layout = game_ui.layout;
+    // The description goes beside the floor so it never hides the cursor
+    let box_x = layout.map_x + map::FLOOR_WIDTH + 1;
+    let width = layout.screen_width - box_x - 1;
+    loop {
+        render_all(game_ui, game, actors, false);
+        game_ui.root.set_background(layout.map_x + x, layout.map_y + y,
+                                    game_ui.theme.look_cursor);
+
+        let text = if !game.map[x as usize][y as usize].explored {
+            "You haven't seen this place.".to_string()
+        } else if sight::is_visible(x, y, &game_ui.sight, &game.map) {
+            look_at(x, y, game, actors, &game_ui.sight).join("\n")
+        } else {
+            format!("Out of sight. When you last looked:\n{}",
+                    look_at(x, y, game, actors, &game_ui.sight).join("\n"))
+        };
+        let text = format!("{}\n\nMove the cursor with the movement keys. \
+                            Enter examines, escape stops looking.", text);
+        let height = window::wrap(&text, width as usize).len() as i32;
+        let mut window = Canvas::new(width, height);
+        window.print_wrapped(0, 0, width, &text, colors::WHITE);
+        backend::blit(&window, &mut *game_ui.root, box_x, layout.map_y + 1,
+                      0.7);
+        game_ui.root.flush();
+
+        let key = game_ui.root.wait_for_keypress();
+        if game_ui.root.window_closed() {
+            return;
+        }
+        match (key.code, keymap.action_for(key)) {
+            (KeyCode::Escape, _) | (_, Some(KeyAction::ExamineTile)) => return,
+            (KeyCode::Enter, _) => {
+                if game.map[x as usize][y as usize].explored {
+                    examine_tile(x, y, game, actors, game_ui);
+                }
+            }
+            (_, Some(action)) => if let Some((dx, dy)) = action.direction() {
+                x = cmp::max(0, cmp::min(map::FLOOR_WIDTH - 1, x + dx));
+                y = cmp::max(0, cmp::min(map::FLOOR_HEIGHT - 1, y + dy));
+            },
+            _ => {}
+        }
+    }
+}
+
+fn msgbox(text: &str, width: i32, game_ui: &mut Ui) {
+    window::run_modal(Window::text("", text, width), &mut *game_ui.root,
+                      &game_ui.theme);
+}
+
+/// Ask the player to type a line of text. Enter accepts it, escape cancels.
+fn text_input(header: &str, max_length: usize, width: i32, game_ui: &mut Ui)
+              -> Option<String> {
+    match window::run_modal(Window::text_input(header, max_length, width,
+                                               Purpose::Info),
+                            &mut *game_ui.root, &game_ui.theme) {
+        Outcome::Entered(text) => Some(text),
+        _ => None,
+    }
 }
 
-fn msgbox(text: &str, width: i32, root: &mut Root) {
-    let options: &[&str] = &[];
-    menu(text, options, width, root);
+/// Walk the player through naming their character and choosing what
+/// they did before the outbreak. Returns the name and background index.
+fn character_creation(backgrounds: &[Background], game_ui: &mut Ui)
+                      -> Option<(String, usize)> {
+    let name = match text_input("What is your name?", MAX_NAME_LENGTH,
+                                consts::INVENTORY_WIDTH, game_ui) {
+        Some(ref name) if name.trim().is_empty() => "Survivor".into(),
+        Some(name) => name.trim().to_string(),
+        None => return None,
+    };
+    let options: Vec<_> = backgrounds.iter().map(|background| {
+        format!("{}: {}", background.name, background.description)
+    }).collect();
+    menu("What did you do before the outbreak?\n", &options,
+         consts::INVENTORY_WIDTH, game_ui)
+        .map(|background_id| (name, background_id))
+}
+
+/// Let the player change how the game is shown. Every change is saved and
+/// takes effect straight away.
+fn options_menu(game_ui: &mut Ui) {
+    loop {
+        let font_id = game_ui.fonts.iter()
+            .position(|font| font.file == game_ui.settings.font)
+            .unwrap_or(0);
+        let size = (game_ui.settings.screen_width,
+                    game_ui.settings.screen_height);
+        let size_id = settings::SCREEN_SIZES.iter()
+            .position(|&screen_size| screen_size == size);
+        let options = [
+            format!("Font: {}", game_ui.fonts[font_id].name),
+            format!("Window size: {}x{}", size.0, size.1),
+            format!("Fullscreen: {}",
+                    if game_ui.settings.fullscreen { "on" } else { "off" }),
+            format!("Messages and stats: {}", game_ui.settings.panel.name()),
+            format!("Colors: {}", game_ui.theme.name),
+        ];
+        let choice = menu("Options\n\nPick a setting to change it, or any \
+                           other key to go back.\n",
+                          &options, consts::INVENTORY_WIDTH,
+                          game_ui);
+
+        match choice {
+            Some(0) => {
+                let font = &game_ui.fonts[(font_id + 1) % game_ui.fonts.len()];
+                game_ui.settings.font = font.file.clone();
+            }
+            Some(1) => {
+                // A size set by hand in the file starts over from the first
+                let next = size_id.map_or(0, |id| {
+                    (id + 1) % settings::SCREEN_SIZES.len()
+                });
+                let (width, height) = settings::SCREEN_SIZES[next];
+                game_ui.settings.screen_width = width;
+                game_ui.settings.screen_height = height;
+            }
+            Some(2) => game_ui.settings.fullscreen = !game_ui.settings.fullscreen,
+            Some(3) => game_ui.settings.panel = match game_ui.settings.panel {
+                PanelPosition::Bottom => PanelPosition::Top,
+                PanelPosition::Top => PanelPosition::Bottom,
+            },
+            Some(4) => {
+                let theme_id = game_ui.themes.iter()
+                    .position(|theme| theme.name == game_ui.theme.name)
+                    .unwrap_or(0);
+                let theme = &game_ui.themes[(theme_id + 1) %
+                                            game_ui.themes.len()];
+                game_ui.settings.theme = Some(theme.name.clone());
+            }
+            _ => return,
+        }
+        apply_settings(game_ui);
+        if let Err(e) = settings::save_settings(&game_ui.settings,
+                                                settings::SETTINGS_FILE) {
+            msgbox(&format!("\nCouldn't save the settings: {}\n", e),
+                   consts::INVENTORY_WIDTH, game_ui);
+        }
+    }
 }
 
 pub fn main_menu(game_ui: &mut Ui) {
-    let img = tcod::image::Image::from_file("menu_background.png")
-        .ok().expect("Background image not found");
+    let mut keymap_problems = vec![];
+    let loaded = game::load_data().and_then(|data| {
+        keymap::load_keymap(&mut keymap_problems).map(|keymap| (data, keymap))
+    });
+    let (data, keymap) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            msgbox(&format!("\nGame data failed to load: {}\n", e),
+                   consts::INVENTORY_WIDTH, game_ui);
+            return;
+        }
+    };
+    // The player's own keymap falls back on the defaults, but they should
+    // know why their keys don't work
+    for &(ref problem, _) in &keymap_problems {
+        msgbox(&format!("\n{}\n", problem), consts::INVENTORY_WIDTH,
+               game_ui);
+    }
 
     while !game_ui.root.window_closed() {
-        tcod::image::blit_2x(&img, (0, 0), (-1, -1), &mut game_ui.root, (0, 0));
-        game_ui.root.set_default_foreground(colors::LIGHT_YELLOW);
-        game_ui.root.print_ex(consts::SCREEN_WIDTH/2, consts::SCREEN_HEIGHT/2 - 4,
-                           BackgroundFlag::None, TextAlignment::Center,
-                           "RustWorld");
-        game_ui.root.print_ex(consts::SCREEN_WIDTH/2, consts::SCREEN_HEIGHT/2 - 2,
-                           BackgroundFlag::None, TextAlignment::Center,
-                           "Meow");
-
-        let choices = &["Play a new game", "Continue last game", "Quit"];
-        let choice = menu("", choices, 24, &mut game_ui.root);
+        game_ui.root.draw_image("menu_background.png");
+        let (center_x, center_y) = (game_ui.layout.screen_width / 2,
+                                    game_ui.layout.screen_height / 2);
+        game_ui.root.print_centered(center_x, center_y - 4, "RustWorld",
+                                    colors::LIGHT_YELLOW);
+        game_ui.root.print_centered(center_x, center_y - 2, "Meow",
+                                    colors::LIGHT_YELLOW);
+
+        let choices = &["Play a new game", "Continue last game", "Options",
+                        "Quit"];
+        let choice = menu("", choices, 24, game_ui);
 
         match choice {
             Some(0) => {
-                let (mut actors, mut game) = game::new_game(game_ui);
-                game::play_game(&mut actors, &mut game, game_ui);
+                let created = character_creation(&data.backgrounds,
+                                                 game_ui);
+                if let Some((name, background_id)) = created {
+                    let background = &data.backgrounds[background_id];
+                    let seed = rng::reseed();
+                    let (mut actors, mut game) = game::new_game(&name,
+                                                                background,
+                                                                &data);
+                    initialize_fov(&game.map, &actors, game_ui);
+                    let start = replay::Start::NewGame(name,
+                                                       background.name.clone());
+                    let recorder = replay::Recorder::create(
+                        replay::REPLAY_FILE, seed, start);
+                    game::play_game(&mut actors, &mut game, &data, &keymap,
+                                    game_ui, recorder);
+                }
             }
             Some(1) => {
-                match game::load_game() {
-                    Ok((mut actors, mut game)) => {
+                let loaded = game::read_save().and_then(|save| {
+                    game::decode_save(&save).map(|decoded| (save, decoded))
+                });
+                match loaded {
+                    Ok((save, (mut actors, mut game))) => {
+                        let seed = rng::reseed();
                         initialize_fov(&game.map, &actors, game_ui);
-                        game::play_game(&mut actors, &mut game, game_ui);
+                        let recorder = replay::Recorder::create(
+                            replay::REPLAY_FILE, seed,
+                            replay::Start::SavedGame(save));
+                        game::play_game(&mut actors, &mut game, &data,
+                                        &keymap, game_ui, recorder);
                     }
                     Err(_e) => {
                         msgbox("\nSaved game failed to load.\n",
-                               24, &mut game_ui.root);
+                               24, game_ui);
                         continue;
                     }
                 }
             }
-            Some(2) => {
+            Some(2) => options_menu(game_ui),
+            Some(3) => {
                 break;
             }
             _ => {}
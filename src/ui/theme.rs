@@ -0,0 +1,162 @@
+use rustc_serialize::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+
+use tcod::colors::{self, Color};
+
+/// Palettes the options menu offers besides the default one.
+pub const THEMES_FILE: &'static str = "data/themes.json";
+
+/// Every color the interface draws with, and the objects' own colors it
+/// swaps for others.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub alert: Color,
+    pub dark_ground: Color,
+    pub dark_wall: Color,
+    pub fire: Color,
+    pub fire_background: Color,
+    pub hp_bar: Color,
+    pub hp_bar_back: Color,
+    pub info: Color,
+    pub light_ground: Color,
+    pub light_wall: Color,
+    pub look_cursor: Color,
+    pub name: String,
+    pub overview_container: Color,
+    pub overview_door: Color,
+    pub overview_floor: Color,
+    pub overview_looted: Color,
+    pub overview_monster: Color,
+    pub overview_stairs: Color,
+    pub overview_wall: Color,
+    // Object colors drawn as another color instead, e.g. red and green
+    pub recolor: Vec<(Color, Color)>,
+    pub smoke: Color,
+    pub stamina_bar: Color,
+    pub stamina_bar_back: Color,
+    pub status_change: Color,
+    pub success: Color,
+    pub window_hint: Color,
+    pub window_selected: Color,
+    pub window_tab: Color,
+    pub window_text: Color,
+}
+
+impl Theme {
+    /// The palette the game has always used.
+    pub fn new() -> Self {
+        Theme {
+            alert: colors::RED,
+            dark_ground: colors::DARKER_GREY,
+            dark_wall: colors::BLACK,
+            fire: colors::FLAME,
+            fire_background: colors::DARKER_FLAME,
+            hp_bar: colors::LIGHT_RED,
+            hp_bar_back: colors::DARKER_RED,
+            info: colors::LIGHTER_GREY,
+            light_ground: colors::GREY,
+            light_wall: colors::DARKEST_GREY,
+            look_cursor: colors::LIGHT_YELLOW,
+            name: "Default".into(),
+            overview_container: colors::LIGHT_BLUE,
+            overview_door: colors::DARK_SEPIA,
+            overview_floor: colors::DARK_GREY,
+            overview_looted: colors::DARK_GREEN,
+            overview_monster: colors::RED,
+            overview_stairs: colors::YELLOW,
+            overview_wall: colors::DARKER_GREY,
+            recolor: vec![],
+            smoke: colors::LIGHTER_GREY,
+            stamina_bar: colors::LIGHT_BLUE,
+            stamina_bar_back: colors::DARKER_BLUE,
+            status_change: colors::WHITE,
+            success: colors::GREEN,
+            window_hint: colors::LIGHT_GREY,
+            window_selected: colors::DARK_AZURE,
+            window_tab: colors::GREY,
+            window_text: colors::WHITE,
+        }
+    }
+
+    /// The color to draw an object's `color` with.
+    pub fn object_color(&self, color: Color) -> Color {
+        self.recolor.iter().find(|&&(from, _)| from == color)
+            .map_or(color, |&(_, to)| to)
+    }
+}
+
+/// A theme as written in the themes file. Only the colors that differ
+/// from the default palette need to be listed, and `recolor` pairs up
+/// object colors with what to draw them as.
+#[derive(Debug, RustcDecodable)]
+struct JsonTheme {
+    name: String,
+    colors: HashMap<String, (u8, u8, u8)>,
+    recolor: Option<Vec<((u8, u8, u8), (u8, u8, u8))>>,
+}
+
+#[derive(Debug, RustcDecodable)]
+struct JsonThemes {
+    themes: Vec<JsonTheme>,
+}
+
+fn build_theme(json_theme: JsonTheme) -> Result<Theme, Box<Error>> {
+    let mut theme = Theme::new();
+    theme.name = json_theme.name;
+    for (role, &(r, g, b)) in &json_theme.colors {
+        let color = Color::new(r, g, b);
+        match &role[..] {
+            "alert" => theme.alert = color,
+            "dark_ground" => theme.dark_ground = color,
+            "dark_wall" => theme.dark_wall = color,
+            "fire" => theme.fire = color,
+            "fire_background" => theme.fire_background = color,
+            "hp_bar" => theme.hp_bar = color,
+            "hp_bar_back" => theme.hp_bar_back = color,
+            "info" => theme.info = color,
+            "light_ground" => theme.light_ground = color,
+            "light_wall" => theme.light_wall = color,
+            "look_cursor" => theme.look_cursor = color,
+            "overview_container" => theme.overview_container = color,
+            "overview_door" => theme.overview_door = color,
+            "overview_floor" => theme.overview_floor = color,
+            "overview_looted" => theme.overview_looted = color,
+            "overview_monster" => theme.overview_monster = color,
+            "overview_stairs" => theme.overview_stairs = color,
+            "overview_wall" => theme.overview_wall = color,
+            "smoke" => theme.smoke = color,
+            "stamina_bar" => theme.stamina_bar = color,
+            "stamina_bar_back" => theme.stamina_bar_back = color,
+            "status_change" => theme.status_change = color,
+            "success" => theme.success = color,
+            "window_hint" => theme.window_hint = color,
+            "window_selected" => theme.window_selected = color,
+            "window_tab" => theme.window_tab = color,
+            "window_text" => theme.window_text = color,
+            _ => return Err(From::from(format!("unknown color {} in theme {}",
+                                               role, theme.name))),
+        }
+    }
+    for &((r, g, b), (to_r, to_g, to_b)) in
+        json_theme.recolor.iter().flat_map(|pairs| pairs) {
+            theme.recolor.push((Color::new(r, g, b),
+                                Color::new(to_r, to_g, to_b)));
+        }
+    Ok(theme)
+}
+
+/// The default palette followed by every theme in `filename`.
+pub fn load_themes(filename: &str) -> Result<Vec<Theme>, Box<Error>> {
+    let mut json = String::new();
+    let mut file = try!{ File::open(filename) };
+    try!{ file.read_to_string(&mut json) };
+    let json_themes: JsonThemes = try!{ json::decode(&json) };
+    let mut themes = vec![Theme::new()];
+    for json_theme in json_themes.themes {
+        themes.push(try!{ build_theme(json_theme) });
+    }
+    Ok(themes)
+}
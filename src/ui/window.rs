@@ -0,0 +1,417 @@
+use std::ascii::AsciiExt;
+use std::cmp;
+
+use tcod::colors::Color;
+use tcod::input::{Key, KeyCode};
+
+use consts;
+use ui::backend::{self, Canvas, Screen, Surface};
+use ui::theme::Theme;
+
+/// Most rows of a list or text shown at once, one for each letter.
+const VISIBLE_ROWS: usize = consts::MENU_PAGE_SIZE as usize;
+
+/// What the game does with a window's answer once it has one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Purpose {
+    // Only there to be read
+    Info,
+    UseItem,
+    DropItem,
+    ExamineItem,
+    Quit,
+}
+
+/// How a window was answered.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Cancelled,
+    Chosen(usize),
+    Entered(String),
+    Confirmed,
+}
+
+pub enum Content {
+    // Options picked with their letter, or moved to with the arrows and
+    // picked with enter
+    List {
+        options: Vec<String>,
+        selected: usize,
+        scroll: usize,
+    },
+    // Lines to read, scrolled with the arrows. Lines without a color of
+    // their own use the theme's text color.
+    Text {
+        lines: Vec<(String, Option<Color>)>,
+        scroll: usize,
+    },
+    // Several pages under one header, switched with left, right or tab
+    Tabs {
+        tabs: Vec<(String, Content)>,
+        shown: usize,
+    },
+    TextInput {
+        text: String,
+        max_length: usize,
+    },
+    // A yes or no question
+    Confirm,
+}
+
+/// Split `text` into lines no wider than `width`, breaking between words.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            if !line.is_empty() &&
+                line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(line);
+                line = String::new();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Move `scroll` as little as possible to keep row `row` on screen.
+fn scroll_to(row: usize, scroll: usize) -> usize {
+    if row < scroll {
+        row
+    } else if row >= scroll + VISIBLE_ROWS {
+        row + 1 - VISIBLE_ROWS
+    } else {
+        scroll
+    }
+}
+
+/// Where the arrow and page keys move a position in a list of `len`
+/// rows, or None for any other key.
+fn step(key: Key, position: usize, len: usize) -> Option<usize> {
+    let last = if len == 0 { 0 } else { len - 1 };
+    match key.code {
+        KeyCode::Up => Some(position.saturating_sub(1)),
+        KeyCode::Down => Some(cmp::min(last, position + 1)),
+        KeyCode::PageUp => Some(position.saturating_sub(VISIBLE_ROWS)),
+        KeyCode::PageDown => Some(cmp::min(last, position + VISIBLE_ROWS)),
+        KeyCode::Home => Some(0),
+        KeyCode::End => Some(last),
+        _ => None,
+    }
+}
+
+impl Content {
+    /// React to a key press, returning the outcome once there is one.
+    fn handle_key(&mut self, key: Key) -> Option<Outcome> {
+        match *self {
+            Content::List { ref options, ref mut selected, ref mut scroll } => {
+                if let Some(row) = step(key, *selected, options.len()) {
+                    *selected = row;
+                    *scroll = scroll_to(row, *scroll);
+                    return None;
+                }
+                if key.code == KeyCode::Enter && !options.is_empty() {
+                    return Some(Outcome::Chosen(*selected));
+                }
+                // Letters pick from the rows on screen, anything else
+                // backs out
+                let letter = key.printable.to_ascii_lowercase();
+                if letter.is_alphabetic() && letter.is_ascii() {
+                    let row = *scroll + (letter as usize - 'a' as usize);
+                    if row < options.len() {
+                        return Some(Outcome::Chosen(row));
+                    }
+                }
+                Some(Outcome::Cancelled)
+            }
+            Content::Text { ref lines, ref mut scroll } => {
+                let last_top = lines.len().saturating_sub(VISIBLE_ROWS);
+                match step(key, *scroll, last_top + 1) {
+                    Some(top) => {
+                        *scroll = top;
+                        None
+                    }
+                    None => Some(Outcome::Cancelled),
+                }
+            }
+            Content::Tabs { ref mut tabs, ref mut shown } => {
+                match key.code {
+                    KeyCode::Left => *shown = shown.saturating_sub(1),
+                    KeyCode::Right | KeyCode::Tab =>
+                        *shown = (*shown + 1) % tabs.len(),
+                    _ => return tabs[*shown].1.handle_key(key),
+                }
+                None
+            }
+            Content::TextInput { ref mut text, max_length } => {
+                match key.code {
+                    KeyCode::Enter => return Some(Outcome::Entered(
+                        text.clone())),
+                    KeyCode::Escape => return Some(Outcome::Cancelled),
+                    KeyCode::Backspace => {
+                        text.pop();
+                    }
+                    _ if key.printable != '\0' &&
+                        !key.printable.is_control() &&
+                        text.chars().count() < max_length => {
+                            text.push(key.printable)
+                        }
+                    _ => {}
+                }
+                None
+            }
+            Content::Confirm => {
+                if key.code == KeyCode::Enter ||
+                    key.printable.to_ascii_lowercase() == 'y' {
+                        Some(Outcome::Confirmed)
+                    } else {
+                        Some(Outcome::Cancelled)
+                    }
+            }
+        }
+    }
+
+    /// The rows to draw, each with its color and whether it is
+    /// highlighted.
+    fn rows(&self, theme: &Theme) -> Vec<(String, Color, bool)> {
+        match *self {
+            Content::List { ref options, selected, scroll } => {
+                let mut rows: Vec<_> = options.iter().enumerate()
+                    .skip(scroll).take(VISIBLE_ROWS)
+                    .map(|(row, option)| {
+                        let letter = (b'a' + (row - scroll) as u8) as char;
+                        (format!("({}) {}", letter, option),
+                         theme.window_text, row == selected)
+                    }).collect();
+                if options.len() > VISIBLE_ROWS {
+                    rows.push((format!("{}-{} of {}, arrows scroll",
+                                       scroll + 1,
+                                       scroll + VISIBLE_ROWS, options.len()),
+                               theme.window_hint, false));
+                }
+                rows
+            }
+            Content::Text { ref lines, scroll } => {
+                let mut rows: Vec<_> = lines.iter().skip(scroll)
+                    .take(VISIBLE_ROWS)
+                    .map(|&(ref line, color)| {
+                        (line.clone(), color.unwrap_or(theme.window_text),
+                         false)
+                    })
+                    .collect();
+                if lines.len() > VISIBLE_ROWS {
+                    rows.push(("Arrows scroll, any other key closes."
+                               .to_string(), theme.window_hint, false));
+                }
+                rows
+            }
+            Content::Tabs { ref tabs, shown } => {
+                let names: Vec<_> = tabs.iter().enumerate()
+                    .map(|(id, &(ref name, _))| if id == shown {
+                        format!("[{}]", name)
+                    } else {
+                        format!(" {} ", name)
+                    }).collect();
+                let mut rows = vec![(names.join(" "), theme.window_tab, false),
+                                    ("".to_string(), theme.window_text, false)];
+                rows.extend(tabs[shown].1.rows(theme));
+                rows
+            }
+            Content::TextInput { ref text, .. } => {
+                vec![("".to_string(), theme.window_text, false),
+                     (format!("> {}_", text), theme.window_text, false)]
+            }
+            Content::Confirm => {
+                vec![("".to_string(), theme.window_text, false),
+                     ("(y) Yes  (n) No".to_string(), theme.window_text, false)]
+            }
+        }
+    }
+}
+
+pub struct Window {
+    pub content: Content,
+    pub header: String,
+    // Stay open after a choice, to look over one item after another
+    pub keep_open: bool,
+    pub purpose: Purpose,
+    pub width: i32,
+}
+
+impl Window {
+    pub fn new(header: &str, content: Content, width: i32, purpose: Purpose)
+               -> Self {
+        Window {
+            content: content,
+            header: header.into(),
+            keep_open: false,
+            purpose: purpose,
+            width: width,
+        }
+    }
+
+    pub fn list<T: AsRef<str>>(header: &str, options: &[T], width: i32,
+                               purpose: Purpose) -> Self {
+        let options = options.iter().map(|o| o.as_ref().to_string())
+            .collect();
+        Window::new(header, Content::List { options: options, selected: 0,
+                                            scroll: 0 },
+                    width, purpose)
+    }
+
+    /// Text to read, wrapped to fit the window.
+    pub fn text(header: &str, text: &str, width: i32) -> Self {
+        Window::new(header, text_content(text, width), width, Purpose::Info)
+    }
+
+    pub fn text_input(header: &str, max_length: usize, width: i32,
+                      purpose: Purpose) -> Self {
+        Window::new(header, Content::TextInput { text: String::new(),
+                                                 max_length: max_length },
+                    width, purpose)
+    }
+
+    pub fn confirm(question: &str, purpose: Purpose) -> Self {
+        Window::new(question, Content::Confirm, consts::INVENTORY_WIDTH,
+                    purpose)
+    }
+
+    /// Draw the window in the middle of the screen.
+    pub fn render(&self, root: &mut Screen, theme: &Theme) {
+        let header = if self.header.is_empty() {
+            vec![]
+        } else {
+            wrap(&self.header, self.width as usize)
+        };
+        let rows = self.content.rows(theme);
+        let height = cmp::min(root.height(),
+                              (header.len() + rows.len()) as i32);
+        let mut window = Canvas::new(self.width, cmp::max(1, height));
+        for (y, line) in header.iter().enumerate() {
+            window.print(0, y as i32, line, theme.window_text);
+        }
+        for (row, &(ref line, color, highlighted)) in rows.iter().enumerate() {
+            let y = (header.len() + row) as i32;
+            if highlighted {
+                window.fill_background(0, y, self.width, 1,
+                                       theme.window_selected);
+            }
+            window.print(0, y, line, color);
+        }
+
+        let x = root.width() / 2 - self.width / 2;
+        let y = root.height() / 2 - height / 2;
+        backend::blit(&window, root, x, y, 0.7);
+    }
+}
+
+/// Lines of plain text for a window `width` wide.
+pub fn text_content(text: &str, width: i32) -> Content {
+    Content::Text {
+        lines: wrap(text, width as usize).into_iter()
+            .map(|line| (line, None)).collect(),
+        scroll: 0,
+    }
+}
+
+/// Windows open over the game, the last one on top. Only the top one gets
+/// keys, and the game keeps drawing underneath while they are open.
+pub struct Windows {
+    stack: Vec<Window>,
+}
+
+impl Windows {
+    pub fn new() -> Self {
+        Windows { stack: vec![] }
+    }
+
+    pub fn push(&mut self, window: Window) {
+        self.stack.push(window);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// Pass a key press to the top window. Once it is answered, returns
+    /// what it was for and how it ended.
+    pub fn handle_key(&mut self, key: Key) -> Option<(Purpose, Outcome)> {
+        let outcome = match self.stack.last_mut() {
+            Some(window) => match window.content.handle_key(key) {
+                Some(outcome) => outcome,
+                None => return None,
+            },
+            None => return None,
+        };
+        let purpose = self.stack[self.stack.len() - 1].purpose;
+        let keep_open = self.stack[self.stack.len() - 1].keep_open;
+        if !keep_open || outcome == Outcome::Cancelled {
+            self.stack.pop();
+        }
+        Some((purpose, outcome))
+    }
+
+    pub fn render(&self, root: &mut Screen, theme: &Theme) {
+        for window in &self.stack {
+            window.render(root, theme);
+        }
+    }
+}
+
+/// Show a window on its own and wait for its answer, for questions that
+/// have to be settled before anything else can happen.
+pub fn run_modal(mut window: Window, root: &mut Screen, theme: &Theme)
+                 -> Outcome {
+    loop {
+        window.render(root, theme);
+        root.flush();
+        let key = root.wait_for_keypress();
+        if root.window_closed() {
+            return Outcome::Cancelled;
+        }
+        if let Some(outcome) = window.content.handle_key(key) {
+            return outcome;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wrap;
+
+    #[test]
+    fn short_text_is_one_line() {
+        assert_eq!(wrap("A rusty key.", 20), vec!["A rusty key."]);
+        assert_eq!(wrap("", 20), vec![""]);
+    }
+
+    #[test]
+    fn breaks_between_words() {
+        assert_eq!(wrap("the quick brown fox jumps", 10),
+                   vec!["the quick", "brown fox", "jumps"]);
+        // A word exactly filling the rest of the line still fits
+        assert_eq!(wrap("abcd efghi", 10), vec!["abcd efghi"]);
+    }
+
+    #[test]
+    fn keeps_line_breaks() {
+        assert_eq!(wrap("first\n\nsecond", 20), vec!["first", "", "second"]);
+    }
+
+    #[test]
+    fn long_words_get_a_line_of_their_own() {
+        assert_eq!(wrap("a fire-extinguisher b", 5),
+                   vec!["a", "fire-extinguisher", "b"]);
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        assert_eq!(wrap("crème brûlée", 12), vec!["crème brûlée"]);
+        assert_eq!(wrap("über naïve", 5), vec!["über", "naïve"]);
+    }
+}
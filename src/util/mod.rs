@@ -1,6 +1,7 @@
 use std::cmp;
 
 pub mod owned_weighted_choice;
+pub mod rng;
 
 pub fn mut_two<T>(first_index: usize, second_index: usize, items: &mut [T]) ->
     (&mut T, &mut T) {
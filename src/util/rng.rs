@@ -0,0 +1,37 @@
+use std::cell::RefCell;
+
+use rand::{self, Rng, SeedableRng, XorShiftRng};
+
+thread_local!(static RNG: RefCell<XorShiftRng> =
+              RefCell::new(XorShiftRng::new_unseeded()));
+
+/// Handle to the game's random number generator. Everything that happens
+/// in the game draws from it, so a run can be played again exactly from
+/// the seed it started with.
+#[derive(Clone, Copy, Debug)]
+pub struct GameRng;
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+}
+
+pub fn game_rng() -> GameRng {
+    GameRng
+}
+
+/// Restart the game's random numbers from `seed`.
+pub fn seed(seed: u32) {
+    // XorShift must not be seeded with all zeros
+    let state = [0x193a_6754, 0xa8a7_d469, 0x9783_0e05, seed];
+    RNG.with(|rng| *rng.borrow_mut() = XorShiftRng::from_seed(state));
+}
+
+/// Pick a fresh seed, start the game's random numbers from it and return
+/// it so the run can be recorded.
+pub fn reseed() -> u32 {
+    let new_seed = rand::random();
+    seed(new_seed);
+    new_seed
+}